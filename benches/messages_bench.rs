@@ -12,13 +12,10 @@ fn benchmark_message_passing(c: &mut Criterion) {
             let start_time = std::time::Instant::now();
             exchange.publish(Publish::new(b"Test Message", "test_queue")).unwrap();
 
-            match consumer.receiver().recv().unwrap() {
-                ConsumerMessage::Delivery(delivery) => {
-                    consumer.ack(delivery).unwrap();
-                    let elapsed = start_time.elapsed();
-                    println!("Latency: {:.2?}", elapsed);
-                }
-                _ => {}
+            if let ConsumerMessage::Delivery(delivery) = consumer.receiver().recv().unwrap() {
+                consumer.ack(delivery).unwrap();
+                let elapsed = start_time.elapsed();
+                println!("Latency: {:.2?}", elapsed);
             }
 
             connection.close().unwrap();
@@ -6,7 +6,7 @@ use std::sync::mpsc;
 fn benchmark_producer(c: &mut Criterion) {
     c.bench_function("simulate_price_updates", |b| {
         let (sender, _receiver) = mpsc::channel();
-        b.iter(|| simulate_price_updates(sender.clone(), 1)); // Simulate for 1 second
+        b.iter(|| simulate_price_updates(sender.clone(), 1, Some(42))); // Simulate for 1 second
     });
 }
 
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use crate::decision::{handle_trade_message, Strategy};
+use crate::models::{TradeMessage, TradeResponse};
+use crate::orders::OrderManager;
+use crate::portfolio::Portfolio;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EquityPoint {
+    pub timestamp: String,
+    pub equity: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BacktestResult {
+    pub trades: Vec<TradeResponse>,
+    pub equity_curve: Vec<EquityPoint>,
+    pub portfolio: Portfolio,
+    pub orders: OrderManager,
+    pub messages_processed: usize,
+}
+
+// Feeds messages straight into the strategy and portfolio, in order, with no broker in between.
+pub fn run_backtest<I>(strategy: &dyn Strategy, portfolio: Portfolio, messages: I) -> BacktestResult
+where
+    I: IntoIterator<Item = TradeMessage>,
+{
+    run_backtest_with_orders(strategy, portfolio, OrderManager::new(), messages)
+}
+
+// Same as `run_backtest`, but with a caller-supplied order manager, e.g. one routing to a simulated exchange.
+pub fn run_backtest_with_orders<I>(
+    strategy: &dyn Strategy,
+    mut portfolio: Portfolio,
+    mut orders: OrderManager,
+    messages: I,
+) -> BacktestResult
+where
+    I: IntoIterator<Item = TradeMessage>,
+{
+    let mut trades = Vec::new();
+    let mut equity_curve = Vec::new();
+    let mut messages_processed = 0;
+
+    for message in messages {
+        let responses = handle_trade_message(strategy, &mut portfolio, &mut orders, &message, message.timestamp.clone());
+        messages_processed += 1;
+
+        trades.extend(responses.into_iter().filter(|response| response.quantity > 0));
+        equity_curve.push(EquityPoint {
+            timestamp: message.timestamp,
+            equity: portfolio.equity(),
+        });
+    }
+
+    BacktestResult {
+        trades,
+        equity_curve,
+        portfolio,
+        orders,
+        messages_processed,
+    }
+}
@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use crate::journal::{open_json_lines, read_json_lines};
+use crate::models::{TradeAction, TradeResponse};
+
+// One decision as received from the response queue.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BlotterEntry {
+    pub received_at: DateTime<Utc>,
+    // Correlation id of the price update that caused the decision; none for control-channel flattens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    #[serde(flatten)]
+    pub response: TradeResponse,
+}
+
+// Running totals of the decisions seen for one symbol.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SymbolActivity {
+    pub decisions: u64,
+    pub fills: u64,
+    // Shares bought (BUY, COVER) and sold (SELL, SHORT).
+    pub bought: u64,
+    pub sold: u64,
+    pub notional: f64,
+    pub fees: f64,
+    pub last_decision: Option<TradeAction>,
+    pub last_price: f64,
+}
+
+impl SymbolActivity {
+    fn apply(&mut self, response: &TradeResponse) {
+        self.decisions += 1;
+        self.last_decision = Some(response.decision);
+        self.last_price = response.price;
+        if response.quantity == 0 {
+            return;
+        }
+        self.fills += 1;
+        match response.decision {
+            TradeAction::Buy | TradeAction::Cover => self.bought += response.quantity as u64,
+            TradeAction::Sell | TradeAction::Short => self.sold += response.quantity as u64,
+            TradeAction::Refuse => {}
+        }
+        self.notional += response.price * response.quantity as f64;
+        self.fees += response.fee;
+    }
+}
+
+// Live blotter of every decision published by the consumers, persisted as JSON lines so a
+// restarted response consumer picks up where it left off.
+#[derive(Debug)]
+pub struct Blotter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    entries: u64,
+    symbols: BTreeMap<String, SymbolActivity>,
+}
+
+impl Blotter {
+    // Opens (or creates) the blotter file for appending and rebuilds the totals from what it holds.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (existing, file) = open_json_lines::<BlotterEntry, _>(&path, "blotter")?;
+        let mut blotter = Blotter {
+            path,
+            writer: BufWriter::new(file),
+            entries: 0,
+            symbols: BTreeMap::new(),
+        };
+        for entry in &existing {
+            blotter.apply(entry);
+        }
+        Ok(blotter)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> u64 {
+        self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries == 0
+    }
+
+    pub fn symbols(&self) -> &BTreeMap<String, SymbolActivity> {
+        &self.symbols
+    }
+
+    // Writes the entry before counting it, so the totals never include anything not on disk.
+    pub fn record(&mut self, entry: &BlotterEntry) -> io::Result<()> {
+        let line = serde_json::to_string(entry).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        self.apply(entry);
+        Ok(())
+    }
+
+    fn apply(&mut self, entry: &BlotterEntry) {
+        self.entries += 1;
+        self.symbols
+            .entry(entry.response.stock_id.clone())
+            .or_default()
+            .apply(&entry.response);
+    }
+
+    pub fn display_summary(&self) {
+        println!("\n--- Blotter ({} decisions in {}) ---\n", self.entries, self.path.display());
+        println!(
+            "{:<6} {:>9} {:>6} {:>8} {:>8} {:>12} {:>9}  {:<7} {:>10}",
+            "Symbol", "Decisions", "Fills", "Bought", "Sold", "Notional", "Fees", "Last", "Last Price"
+        );
+        for (stock_id, activity) in &self.symbols {
+            println!(
+                "{:<6} {:>9} {:>6} {:>8} {:>8} {:>12.2} {:>9.2}  {:<7} {:>10.2}",
+                stock_id,
+                activity.decisions,
+                activity.fills,
+                activity.bought,
+                activity.sold,
+                activity.notional,
+                activity.fees,
+                activity.last_decision.map(|decision| decision.as_str()).unwrap_or("-"),
+                activity.last_price
+            );
+        }
+        let fills: u64 = self.symbols.values().map(|activity| activity.fills).sum();
+        let notional: f64 = self.symbols.values().map(|activity| activity.notional).sum();
+        let fees: f64 = self.symbols.values().map(|activity| activity.fees).sum();
+        println!("\nFills: {}, Notional: ${:.2}, Fees: ${:.2}", fills, notional, fees);
+    }
+}
+
+pub fn read_blotter<P: AsRef<Path>>(path: P) -> io::Result<Vec<BlotterEntry>> {
+    read_json_lines(path, "blotter")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(stock_id: &str, decision: TradeAction, quantity: u32) -> BlotterEntry {
+        BlotterEntry {
+            received_at: Utc::now(),
+            correlation_id: None,
+            response: TradeResponse {
+                stock_id: stock_id.to_string(),
+                decision,
+                quantity,
+                price: 100.0,
+                quoted_price: 100.0,
+                fee: 1.0,
+                timestamp: "2024-01-02T15:00:00Z".to_string(),
+                order_id: None,
+                order_status: None,
+                rejection: None,
+            },
+        }
+    }
+
+    #[test]
+    fn blotter_survives_a_crash_mid_write_and_restart() {
+        let path = std::env::temp_dir().join(format!("blotter-test-crash-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut blotter = Blotter::open(&path).unwrap();
+        blotter.record(&entry("AAPL", TradeAction::Buy, 10)).unwrap();
+        blotter.record(&entry("AAPL", TradeAction::Sell, 4)).unwrap();
+        drop(blotter);
+
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"received_at\":\"2024-01-0").unwrap();
+        drop(file);
+
+        let mut blotter = Blotter::open(&path).unwrap();
+        assert_eq!(blotter.len(), 2);
+        blotter.record(&entry("MSFT", TradeAction::Buy, 5)).unwrap();
+        drop(blotter);
+
+        let reloaded = Blotter::open(&path).unwrap();
+        assert_eq!(reloaded.len(), 3);
+        assert_eq!(reloaded.symbols()["AAPL"].bought, 10);
+        assert_eq!(reloaded.symbols()["AAPL"].sold, 4);
+        assert_eq!(reloaded.symbols()["MSFT"].bought, 5);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
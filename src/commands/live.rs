@@ -0,0 +1,732 @@
+use amiquip::{
+    AmqpProperties, AmqpValue, Channel, Connection, ConsumerMessage, ConsumerOptions, Delivery, Exchange,
+    ExchangeDeclareOptions, ExchangeType, FieldTable, Publish, Queue, QueueDeclareOptions,
+};
+use chrono::Utc;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, TrySendError};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+use trades_subsystem::backtest::EquityPoint;
+use trades_subsystem::blotter::{Blotter, BlotterEntry};
+use trades_subsystem::config::Config;
+use trades_subsystem::control::{install_signal_handlers, shutdown_signal, spawn_socket_listener, ControlCommand};
+use trades_subsystem::decision::{handle_trade_message, Strategy};
+use trades_subsystem::journal::{read_journal, replay_entries, verify_snapshot, Journal};
+use trades_subsystem::metrics::PerformanceReport;
+use trades_subsystem::models::{TradeMessage, TradeResponse};
+use trades_subsystem::orders::OrderManager;
+use trades_subsystem::portfolio::Portfolio;
+use trades_subsystem::producer::{
+    load_ohlcv_files, random_drift, replay_price_updates, routing_key, run_price_simulation, PriceModel,
+    PriceSimulator,
+};
+use trades_subsystem::snapshot::{load_snapshot, Autosaver};
+use trades_subsystem::workers::WorkerPool;
+use super::{create_strategy, new_order_manager, new_portfolio, print_report, CommandResult};
+use crate::{BlotterArgs, ProducerArgs, RunArgs, SessionArgs, TradingArgs};
+
+// AMQP delivery mode for messages the broker writes to disk in durable queues.
+const PERSISTENT: u8 = 2;
+
+// How long the producer keeps listening for replies after its last price update.
+const REPLY_GRACE: Duration = Duration::from_secs(2);
+
+// An update with no reply after this long is counted as unanswered and no longer tracked, so
+// updates nobody consumes (no matching binding, no consumer running) don't pile up all run.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Where the decisions for one price update are answered, taken from its AMQP properties.
+#[derive(Debug, Clone, Default)]
+struct ReplyAddress {
+    correlation_id: Option<String>,
+    reply_to: Option<String>,
+}
+
+impl ReplyAddress {
+    fn from_delivery(delivery: &Delivery) -> Self {
+        ReplyAddress {
+            correlation_id: delivery.properties.correlation_id().clone(),
+            reply_to: delivery.properties.reply_to().clone(),
+        }
+    }
+}
+
+fn declare_price_exchange<'a>(channel: &'a Channel, config: &Config) -> amiquip::Result<Exchange<'a>> {
+    channel.exchange_declare(
+        ExchangeType::Topic,
+        config.broker.exchange.as_str(),
+        ExchangeDeclareOptions {
+            durable: true,
+            ..ExchangeDeclareOptions::default()
+        },
+    )
+}
+
+// Declares the consumer's price queue and binds it to the exchange with each configured pattern.
+// Consumers sharing the queue split its updates; a queue of its own gets a full copy.
+fn declare_price_queue<'a>(channel: &'a Channel, config: &Config) -> amiquip::Result<Queue<'a>> {
+    let exchange = declare_price_exchange(channel, config)?;
+    let queue = channel.queue_declare(config.price_queue().as_str(), QueueDeclareOptions::default())?;
+    for pattern in &config.consumer.bindings {
+        queue.bind(&exchange, pattern.as_str(), FieldTable::default())?;
+    }
+    Ok(queue)
+}
+
+// Declares the durable response queue, dead-lettering to a durable fanout exchange whose own queue
+// keeps whatever the blotter rejects. Publishers and the blotter both declare it, with the same
+// arguments, so neither depends on the other having started first.
+fn declare_response_queue<'a>(channel: &'a Channel, config: &Config) -> amiquip::Result<Queue<'a>> {
+    let durable = QueueDeclareOptions {
+        durable: true,
+        ..QueueDeclareOptions::default()
+    };
+    let dead_letter_exchange = channel.exchange_declare(
+        ExchangeType::Fanout,
+        config.broker.dead_letter_exchange.as_str(),
+        ExchangeDeclareOptions {
+            durable: true,
+            ..ExchangeDeclareOptions::default()
+        },
+    )?;
+    let dead_letters = channel.queue_declare(config.queues.dead_letters.as_str(), durable.clone())?;
+    dead_letters.bind(&dead_letter_exchange, "", FieldTable::default())?;
+
+    let mut arguments = FieldTable::default();
+    arguments.insert(
+        "x-dead-letter-exchange".to_string(),
+        AmqpValue::LongString(config.broker.dead_letter_exchange.clone()),
+    );
+    channel.queue_declare(
+        config.queues.responses.as_str(),
+        QueueDeclareOptions { arguments, ..durable },
+    )
+}
+
+// Runs the producer on its own connection next to the consumer, and stops it when the consumer is done.
+// The producer waits until the consumer's queue is bound, since the exchange drops unroutable updates.
+pub fn run(config: &Config, args: &RunArgs) -> CommandResult {
+    let stop = Arc::new(AtomicBool::new(false));
+    let (ready_sender, ready_receiver) = mpsc::channel();
+    let producer = {
+        let config = config.clone();
+        let producer_args = args.producer.clone();
+        let stop = Arc::clone(&stop);
+        thread::spawn(move || match ready_receiver.recv() {
+            Ok(()) => produce(&config, &producer_args, Some(&stop)),
+            // The consumer failed before it was ready and reports why itself.
+            Err(_) => Ok(()),
+        })
+    };
+
+    let duration = Duration::from_secs(config.producer.duration_secs);
+    let result = consume(config, &args.trading, &args.session, Some(duration), Some(ready_sender));
+    stop.store(true, Ordering::SeqCst);
+    match producer.join() {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => eprintln!("[ERROR] Producer failed: {}", e),
+        Err(_) => eprintln!("[ERROR] Producer panicked."),
+    }
+    result
+}
+
+// Publishes simulated or replayed prices until the source runs out, a shutdown signal arrives or `stop` is set.
+pub fn produce(config: &Config, args: &ProducerArgs, stop: Option<&AtomicBool>) -> CommandResult {
+    if let Err(e) = install_signal_handlers() {
+        eprintln!("[WARN] Could not install SIGINT/SIGTERM handlers: {}", e);
+    }
+
+    let mut connection = Connection::insecure_open(&config.broker.url)?;
+    let channel = connection.open_channel(None)?;
+    let exchange = declare_price_exchange(&channel, config)?;
+    // Replies come back on a queue only this producer can see; the broker picks its name, which
+    // also prefixes the correlation ids so they stay unique across producers.
+    let reply_queue = channel.queue_declare(
+        "",
+        QueueDeclareOptions {
+            exclusive: true,
+            ..QueueDeclareOptions::default()
+        },
+    )?;
+    let reply_consumer = reply_queue.consume(ConsumerOptions {
+        no_ack: true,
+        ..ConsumerOptions::default()
+    })?;
+    let mut pending: HashMap<String, Instant> = HashMap::new();
+    let mut published = 0u64;
+    let mut answered = 0u64;
+    let mut expired = 0u64;
+
+    let (price_update_sender, price_update_receiver) = mpsc::channel();
+    if args.replay_files.is_empty() {
+        let seed = config.producer.seed.unwrap_or_else(rand::random);
+        println!("[Producer] Using simulation seed {}.", seed);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let model = PriceModel {
+            drift: config.producer.drift.unwrap_or_else(|| random_drift(&mut rng)),
+            volatility: config.producer.volatility,
+            jumps: config.producer.jumps(),
+            ..PriceModel::default()
+        };
+        let simulator = PriceSimulator::with_universe(rng, model, config.producer.symbols.clone());
+        let duration_secs = config.producer.duration_secs;
+        let tick_interval = Duration::from_millis(config.producer.tick_interval_ms);
+        thread::spawn(move || run_price_simulation(price_update_sender, duration_secs, tick_interval, simulator));
+    } else {
+        let bars = load_ohlcv_files(&args.replay_files).map_err(|e| format!("Failed to load replay data: {}", e))?;
+        let speed = args.replay_speed;
+        thread::spawn(move || replay_price_updates(price_update_sender, bars, speed));
+    }
+
+    let stopped = || stop.is_some_and(|stop| stop.load(Ordering::SeqCst));
+    while shutdown_signal().is_none() && !stopped() {
+        for delivery in reply_consumer.receiver().try_iter() {
+            if let ConsumerMessage::Delivery(delivery) = delivery {
+                answered += record_reply(&delivery, &mut pending);
+            }
+        }
+        expired += expire_pending(&mut pending, REPLY_TIMEOUT);
+
+        let message = match price_update_receiver.recv_timeout(Duration::from_millis(100)) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        match serde_json::to_string(&message) {
+            Ok(payload) => {
+                println!(
+                    "[Producer] Updated Price for {:<5}: ${:.2} at Quantity: {} at {}",
+                    message.stock_id, message.current_price, message.quantity, message.timestamp
+                );
+                let correlation_id = format!("{}-{}", reply_queue.name(), published + 1);
+                let properties = AmqpProperties::default()
+                    .with_correlation_id(correlation_id.clone())
+                    .with_reply_to(reply_queue.name().to_string());
+                let publish = Publish::with_properties(payload.as_bytes(), routing_key(&message.stock_id), properties);
+                match exchange.publish(publish) {
+                    Ok(()) => {
+                        published += 1;
+                        pending.insert(correlation_id, Instant::now());
+                    }
+                    Err(e) => eprintln!("[ERROR] Failed to publish message: {}", e),
+                }
+            }
+            Err(e) => eprintln!("[ERROR] Failed to serialize message: {}", e),
+        }
+    }
+
+    // Consumers may still be working through the tail of the updates. When `stop` was set the
+    // consumer in this process has already finished, so there is nothing left to wait for.
+    let deadline = Instant::now() + REPLY_GRACE;
+    while !pending.is_empty() && shutdown_signal().is_none() && !stopped() && Instant::now() < deadline {
+        if let Ok(ConsumerMessage::Delivery(delivery)) = reply_consumer.receiver().recv_timeout(Duration::from_millis(100)) {
+            answered += record_reply(&delivery, &mut pending);
+        }
+    }
+    println!(
+        "[Producer] Published {} price updates; {} answered, {} unanswered.",
+        published,
+        answered,
+        expired + pending.len() as u64
+    );
+
+    reply_consumer.cancel()?;
+    connection.close()?;
+    Ok(())
+}
+
+// Stops tracking updates sent more than `timeout` ago; returns how many were dropped.
+fn expire_pending(pending: &mut HashMap<String, Instant>, timeout: Duration) -> u64 {
+    let before = pending.len();
+    pending.retain(|_, sent_at| sent_at.elapsed() < timeout);
+    (before - pending.len()) as u64
+}
+
+// Logs a decision sent back for one of our updates. Returns 1 for the first reply to an update;
+// later ones for the same update (fills of resting orders) are logged but not counted.
+fn record_reply(delivery: &Delivery, pending: &mut HashMap<String, Instant>) -> u64 {
+    let correlation_id = delivery.properties.correlation_id().clone().unwrap_or_default();
+    let response = match serde_json::from_slice::<TradeResponse>(&delivery.body) {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("[ERROR] Failed to parse reply {}: {}", correlation_id, e);
+            return 0;
+        }
+    };
+    match pending.remove(&correlation_id) {
+        Some(sent_at) => {
+            println!(
+                "[Producer] Reply {} after {} ms: {} {} {} at ${:.2}",
+                correlation_id,
+                sent_at.elapsed().as_millis(),
+                response.decision,
+                response.quantity,
+                response.stock_id,
+                response.price
+            );
+            1
+        }
+        None => {
+            println!(
+                "[Producer] Reply {}: {} {} {} at ${:.2}",
+                correlation_id, response.decision, response.quantity, response.stock_id, response.price
+            );
+            0
+        }
+    }
+}
+
+// A decision that panicked poisons the shared locks. Decisions, control commands and shutdown
+// all carry on with the state it left, so the pending responses and the summary still go out.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// Applies one control-channel command; returns true when it asks the session to shut down.
+fn apply_control(
+    command: ControlCommand,
+    portfolio: &Mutex<Portfolio>,
+    orders: &Mutex<OrderManager>,
+    responses: &mpsc::Sender<(TradeResponse, ReplyAddress)>,
+) -> bool {
+    println!("[Control] Received command: {}", command);
+    match command {
+        ControlCommand::HaltAll => lock(orders).halt_all(),
+        ControlCommand::HaltSymbol(stock_id) => lock(orders).halt_symbol(&stock_id),
+        ControlCommand::Resume(stock_id) => lock(orders).resume(stock_id.as_deref()),
+        ControlCommand::FlattenAll => {
+            let mut portfolio = lock(portfolio);
+            let mut orders = lock(orders);
+            for response in orders.flatten_all(&mut portfolio, &Utc::now().to_rfc3339()) {
+                if let Err(e) = responses.send((response, ReplyAddress::default())) {
+                    eprintln!("[ERROR] Failed to send trade response: {}", e);
+                }
+            }
+        }
+        ControlCommand::Shutdown => return true,
+    }
+    false
+}
+
+// The journal is the source of truth: it also holds everything after the last snapshot.
+fn open_portfolio(config: &Config, trading: &TradingArgs, session: &SessionArgs) -> Result<Portfolio, String> {
+    let journal = match &session.journal {
+        Some(path) => Some(
+            Journal::open(path, session.journal_fsync)
+                .map_err(|e| format!("Failed to open journal {}: {}", path.display(), e))?,
+        ),
+        None => None,
+    };
+
+    let snapshot = match &session.resume {
+        Some(path) => {
+            let snapshot = load_snapshot(path).map_err(|e| format!("Failed to load snapshot {}: {}", path.display(), e))?;
+            println!("Loaded snapshot {} saved at {}.", path.display(), snapshot.saved_at);
+            Some(snapshot)
+        }
+        None => None,
+    };
+
+    let mut portfolio = match (&journal, snapshot) {
+        (Some(journal), snapshot) if !journal.is_empty() => {
+            let path = journal.path();
+            if let Some(snapshot) = &snapshot {
+                match verify_snapshot(path, snapshot) {
+                    Ok(differences) if differences.is_empty() => {
+                        println!("Journal replay matches snapshot at sequence {:?}.", snapshot.journal_seq)
+                    }
+                    Ok(differences) => {
+                        eprintln!("[WARN] Journal replay differs from snapshot:");
+                        for difference in differences {
+                            eprintln!("  {}", difference);
+                        }
+                    }
+                    Err(e) => eprintln!("[WARN] Could not verify snapshot against journal: {}", e),
+                }
+            }
+            let portfolio = read_journal(path)
+                .and_then(|entries| replay_entries(&entries, None))
+                .map_err(|e| format!("Failed to replay journal {}: {}", path.display(), e))?;
+            println!("Recovered portfolio from {} journal events in {}.", journal.last_seq(), path.display());
+            portfolio
+        }
+        (_, Some(snapshot)) => snapshot.portfolio,
+        (_, None) => new_portfolio(config, trading)?,
+    };
+    if let Some(journal) = journal {
+        portfolio.attach_journal(journal);
+    }
+    Ok(portfolio)
+}
+
+// Trades on price updates from the broker until `duration` elapses, a shutdown signal arrives or
+// the control channel asks to shut down. `ready` is signalled once the price queue is bound.
+pub fn consume(
+    config: &Config,
+    trading: &TradingArgs,
+    session: &SessionArgs,
+    duration: Option<Duration>,
+    ready: Option<mpsc::Sender<()>>,
+) -> CommandResult {
+    let strategy: Arc<dyn Strategy> = Arc::from(create_strategy(config)?);
+    println!("Using strategy: {}", strategy.name());
+
+    let mut connection = Connection::insecure_open(&config.broker.url)?;
+    let channel = connection.open_channel(None)?;
+    // Every consumer on the same queue takes a share of its updates, so running more of them
+    // spreads the load across processes; each one keeps its own portfolio.
+    let queue = declare_price_queue(&channel, config)?;
+    declare_response_queue(&channel, config)?;
+    println!("Consuming {} bound to {}.", config.price_queue(), config.consumer.bindings.join(", "));
+    if let Some(ready) = ready {
+        let _ = ready.send(());
+    }
+
+    let initial_portfolio = open_portfolio(config, trading, session)?;
+    let initial_equity = initial_portfolio.equity();
+    let portfolio = Arc::new(Mutex::new(initial_portfolio));
+    let orders = Arc::new(Mutex::new(new_order_manager(config, trading)));
+    let mut autosaver = session
+        .snapshot
+        .clone()
+        .map(|path| Autosaver::new(path, Duration::from_secs(session.autosave_secs)));
+    let mut trade_log = Vec::new();
+    let mut equity_curve = Vec::new();
+
+    let (trade_response_sender, trade_response_receiver) = mpsc::channel();
+
+    match duration {
+        Some(duration) => println!("\nStarting simulation for {} seconds...\n", duration.as_secs()),
+        None => println!("\nConsuming price updates until SIGINT, SIGTERM or `shutdown`...\n"),
+    }
+    let start_time = Instant::now();
+
+    // Deliveries are only acknowledged once a worker has queued them, so the prefetch window is what
+    // stops the broker from pushing more while the workers are backed up.
+    channel.qos(0, config.consumer.prefetch, false)?;
+    let rabbitmq_consumer = queue.consume(ConsumerOptions::default())?;
+    // A kill switch has to reach every instance, so each consumer gets its own broker-named queue
+    // on the control fanout rather than competing with the others for one shared queue.
+    let control_exchange = channel.exchange_declare(
+        ExchangeType::Fanout,
+        config.broker.control_exchange.as_str(),
+        ExchangeDeclareOptions {
+            durable: true,
+            ..ExchangeDeclareOptions::default()
+        },
+    )?;
+    let control_queue = channel.queue_declare(
+        "",
+        QueueDeclareOptions {
+            exclusive: true,
+            ..QueueDeclareOptions::default()
+        },
+    )?;
+    control_queue.bind(&control_exchange, "", FieldTable::default())?;
+    let control_consumer = control_queue.consume(ConsumerOptions::default())?;
+
+    if let Err(e) = install_signal_handlers() {
+        eprintln!("[WARN] Could not install SIGINT/SIGTERM handlers: {}", e);
+    }
+
+    let (control_sender, control_receiver) = mpsc::channel();
+    if let Some(path) = &session.control_socket {
+        spawn_socket_listener(path, control_sender)
+            .map_err(|e| format!("Failed to open control socket {}: {}", path.display(), e))?;
+        println!("Listening for control commands on {}.", path.display());
+    }
+
+    // Logs a decision, records it for the performance report and publishes it, tagged with the
+    // correlation id of the update that caused it and also sent to that update's reply queue.
+    let response_exchange = Exchange::direct(&channel);
+    let mut record_response = |response: TradeResponse, reply: ReplyAddress| {
+        let fill = if response.quantity > 0 && (response.price - response.quoted_price).abs() > f64::EPSILON {
+            format!(" (quoted ${:.2})", response.quoted_price)
+        } else {
+            String::new()
+        };
+        let order = match (response.order_id, response.order_status, &response.rejection) {
+            (Some(id), Some(status), Some(rejection)) => format!(", Order: #{} {} ({})", id, status, rejection),
+            (Some(id), Some(status), None) => format!(", Order: #{} {}", id, status),
+            _ => String::new(),
+        };
+        println!(
+            "[Producer] Trade Decision Received: Stock: {}, Action: {}, Quantity: {}, Price: ${:.2}{}, Timestamp: {}{}\n",
+            response.stock_id,
+            response.decision,
+            response.quantity,
+            response.price,
+            fill,
+            response.timestamp,
+            order
+        );
+
+        equity_curve.push(EquityPoint {
+            timestamp: response.timestamp.clone(),
+            equity: lock(&portfolio).equity(),
+        });
+
+        if let Ok(payload) = serde_json::to_string(&response) {
+            let properties = AmqpProperties::default().with_delivery_mode(PERSISTENT);
+            let properties = match &reply.correlation_id {
+                Some(correlation_id) => properties.with_correlation_id(correlation_id.clone()),
+                None => properties,
+            };
+            let mut routing_keys = vec![config.queues.responses.as_str()];
+            routing_keys.extend(reply.reply_to.as_deref().filter(|reply_to| *reply_to != config.queues.responses));
+            for routing_key in routing_keys {
+                let publish = Publish::with_properties(payload.as_bytes(), routing_key, properties.clone());
+                if let Err(e) = response_exchange.publish(publish) {
+                    eprintln!("[ERROR] Failed to publish trade response to {}: {}", routing_key, e);
+                }
+            }
+        } else {
+            eprintln!("[ERROR] Failed to serialize trade response.");
+        }
+        if response.quantity > 0 {
+            trade_log.push(response);
+        }
+    };
+
+    let pool = {
+        let portfolio = Arc::clone(&portfolio);
+        let orders = Arc::clone(&orders);
+        let tx_response = trade_response_sender.clone();
+        let strategy = Arc::clone(&strategy);
+        let handler = move |(trade_message, reply): (TradeMessage, ReplyAddress)| {
+            let mut portfolio = lock(&portfolio);
+            let mut orders = lock(&orders);
+
+            let responses = handle_trade_message(
+                strategy.as_ref(),
+                &mut portfolio,
+                &mut orders,
+                &trade_message,
+                Utc::now().to_rfc3339(),
+            );
+
+            for response in responses {
+                if let Err(e) = tx_response.send((response, reply.clone())) {
+                    eprintln!("[ERROR] Failed to send trade response: {}", e);
+                }
+            }
+        };
+        WorkerPool::new(config.consumer.workers, config.consumer.queue_capacity, handler)
+    };
+    println!(
+        "Decision workers: {} (queue {} each, prefetch {}).",
+        pool.workers(),
+        config.consumer.queue_capacity,
+        config.consumer.prefetch
+    );
+    // A parsed delivery whose worker queue was full, retried before taking another one.
+    let mut backlog: Option<(TradeMessage, ReplyAddress, Delivery)> = None;
+
+    let mut shutdown_requested = false;
+    while !shutdown_requested
+        && shutdown_signal().is_none()
+        && duration.is_none_or(|duration| Instant::now() - start_time < duration)
+    {
+        let mut commands: Vec<ControlCommand> = control_receiver.try_iter().collect();
+        while let Ok(ConsumerMessage::Delivery(control_message)) = control_consumer.receiver().try_recv() {
+            match String::from_utf8_lossy(&control_message.body).parse::<ControlCommand>() {
+                Ok(command) => commands.push(command),
+                Err(e) => eprintln!("[ERROR] Ignoring control message: {}", e),
+            }
+            control_consumer.ack(control_message)?;
+        }
+        for command in commands {
+            if apply_control(command, &portfolio, &orders, &trade_response_sender) {
+                shutdown_requested = true;
+            }
+        }
+
+        if let Some(autosaver) = autosaver.as_mut() {
+            if autosaver.is_due() {
+                autosaver.save(&lock(&portfolio));
+            }
+        }
+
+        if backlog.is_none() {
+            match rabbitmq_consumer.receiver().try_recv() {
+                Ok(ConsumerMessage::Delivery(rabbitmq_message)) => {
+                    let payload = String::from_utf8_lossy(&rabbitmq_message.body);
+                    match serde_json::from_str::<TradeMessage>(&payload) {
+                        Ok(trade_message) => {
+                            let reply = ReplyAddress::from_delivery(&rabbitmq_message);
+                            backlog = Some((trade_message, reply, rabbitmq_message));
+                        }
+                        Err(e) => {
+                            eprintln!("[ERROR] Failed to parse incoming message: {}", e);
+                            rabbitmq_consumer.ack(rabbitmq_message)?;
+                        }
+                    }
+                }
+                Err(_) => thread::sleep(Duration::from_millis(100)),
+                _ => {}
+            }
+        }
+
+        if let Some((trade_message, reply, rabbitmq_message)) = backlog.take() {
+            let stock_id = trade_message.stock_id.clone();
+            match pool.try_submit(&stock_id, (trade_message, reply)) {
+                Ok(()) => rabbitmq_consumer.ack(rabbitmq_message)?,
+                Err(TrySendError::Full((trade_message, reply))) => {
+                    backlog = Some((trade_message, reply, rabbitmq_message));
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(TrySendError::Disconnected((trade_message, _))) => {
+                    eprintln!("[ERROR] Decision worker for {} has stopped; requeueing.", trade_message.stock_id);
+                    rabbitmq_consumer.nack(rabbitmq_message, true)?;
+                }
+            }
+        }
+
+        for (response, reply) in trade_response_receiver.try_iter() {
+            record_response(response, reply);
+        }
+    }
+
+    // Stop taking new deliveries; anything received but not yet handed to a worker stays unacked
+    // and is redelivered once the channel closes.
+    if let Some((_, _, rabbitmq_message)) = backlog.take() {
+        rabbitmq_consumer.nack(rabbitmq_message, true)?;
+    }
+    rabbitmq_consumer.cancel()?;
+    control_consumer.cancel()?;
+    if pool.in_flight() > 0 {
+        println!("\nWaiting for {} in-flight decisions...", pool.in_flight());
+    }
+    let panicked = pool.shutdown();
+    if panicked > 0 {
+        eprintln!("[ERROR] {} decisions panicked and were skipped.", panicked);
+    }
+    drop(trade_response_sender);
+    for (response, reply) in trade_response_receiver.try_iter() {
+        record_response(response, reply);
+    }
+
+    if let Some(path) = &session.control_socket {
+        let _ = std::fs::remove_file(path);
+    }
+    connection.close()?;
+
+    if let Some(signal) = shutdown_signal() {
+        println!("\nReceived {}, shut down cleanly. Final Portfolio:\n", signal);
+    } else if shutdown_requested {
+        println!("\nShutdown requested from the control channel. Final Portfolio:\n");
+    } else {
+        println!("\nSimulation completed. Final Portfolio:\n");
+    }
+    {
+        let portfolio = lock(&portfolio);
+        portfolio.display_summary();
+        let open_orders: usize = lock(&orders).pending.values().map(|book| book.len()).sum();
+        if open_orders > 0 {
+            println!("Open orders left resting: {}", open_orders);
+        }
+        if trading.lot_report {
+            portfolio.display_realized_gains();
+        }
+        if let Some(autosaver) = autosaver.as_mut() {
+            autosaver.save(&portfolio);
+        }
+    }
+
+    let report = PerformanceReport::compute(initial_equity, &trade_log, &equity_curve);
+    print_report(&report, trading.report_json.as_deref());
+    Ok(())
+}
+
+// Builds a live blotter from the decisions on the response queue, persisting each one before it is
+// acknowledged. Responses that can't be parsed are rejected to the dead-letter queue.
+pub fn blotter(config: &Config, args: &BlotterArgs) -> CommandResult {
+    if let Err(e) = install_signal_handlers() {
+        eprintln!("[WARN] Could not install SIGINT/SIGTERM handlers: {}", e);
+    }
+
+    let mut blotter = Blotter::open(&args.blotter)
+        .map_err(|e| format!("Failed to open blotter {}: {}", args.blotter.display(), e))?;
+    if !blotter.is_empty() {
+        println!("Loaded {} decisions from {}.", blotter.len(), blotter.path().display());
+    }
+
+    let mut connection = Connection::insecure_open(&config.broker.url)?;
+    let channel = connection.open_channel(None)?;
+    channel.qos(0, config.consumer.prefetch, false)?;
+    let queue = declare_response_queue(&channel, config)?;
+    let consumer = queue.consume(ConsumerOptions::default())?;
+    println!("Recording decisions from {} to {}.\n", config.queues.responses, blotter.path().display());
+
+    let start_time = Instant::now();
+    let summary_interval = Duration::from_secs(args.summary_secs.max(1));
+    let mut last_summary = Instant::now();
+    let mut failure = None;
+    while shutdown_signal().is_none()
+        && args.duration_secs.is_none_or(|secs| start_time.elapsed() < Duration::from_secs(secs))
+    {
+        if last_summary.elapsed() >= summary_interval {
+            blotter.display_summary();
+            last_summary = Instant::now();
+        }
+
+        let delivery = match consumer.receiver().recv_timeout(Duration::from_millis(100)) {
+            Ok(ConsumerMessage::Delivery(delivery)) => delivery,
+            Ok(other) => {
+                failure = Some(format!("Response consumer stopped: {:?}", other));
+                break;
+            }
+            Err(_) => continue,
+        };
+        let response = match serde_json::from_slice::<TradeResponse>(&delivery.body) {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("[ERROR] Dead-lettering unparseable trade response: {}", e);
+                consumer.nack(delivery, false)?;
+                continue;
+            }
+        };
+        let entry = BlotterEntry {
+            received_at: Utc::now(),
+            correlation_id: delivery.properties.correlation_id().clone(),
+            response,
+        };
+        // Not acknowledged unless it is on disk; the broker redelivers it to the next blotter.
+        if let Err(e) = blotter.record(&entry) {
+            consumer.nack(delivery, true)?;
+            failure = Some(format!("Failed to write blotter {}: {}", blotter.path().display(), e));
+            break;
+        }
+        consumer.ack(delivery)?;
+
+        let response = &entry.response;
+        let order = match (response.order_id, response.order_status) {
+            (Some(id), Some(status)) => format!(", Order: #{} {}", id, status),
+            _ => String::new(),
+        };
+        println!(
+            "[Blotter] {} {:<6} {:<5} Quantity: {:>4} at ${:.2}, Fee: ${:.2}{} [{}]",
+            response.timestamp,
+            response.decision,
+            response.stock_id,
+            response.quantity,
+            response.price,
+            response.fee,
+            order,
+            entry.correlation_id.as_deref().unwrap_or("-")
+        );
+    }
+
+    consumer.cancel()?;
+    connection.close()?;
+    blotter.display_summary();
+    match failure {
+        Some(failure) => Err(failure.into()),
+        None => Ok(()),
+    }
+}
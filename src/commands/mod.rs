@@ -0,0 +1,75 @@
+pub mod live;
+pub mod offline;
+
+use std::error::Error;
+use std::path::Path;
+use trades_subsystem::config::Config;
+use trades_subsystem::decision::{Strategy, StrategyRegistry};
+use trades_subsystem::exchange::{LiquidityConfig, MatchingEngine};
+use trades_subsystem::margin::MarginConfig;
+use trades_subsystem::metrics::PerformanceReport;
+use trades_subsystem::orders::OrderManager;
+use trades_subsystem::portfolio::Portfolio;
+use trades_subsystem::risk::{RiskEngine, RiskLimits};
+use crate::TradingArgs;
+
+pub type CommandResult = Result<(), Box<dyn Error + Send + Sync>>;
+
+fn create_strategy(config: &Config) -> Result<Box<dyn Strategy>, String> {
+    let registry = StrategyRegistry::with_thresholds(config.strategy.params.clone());
+    registry.create(&config.strategy.name).ok_or_else(|| {
+        format!(
+            "Unknown strategy '{}'. Available strategies: {}",
+            config.strategy.name,
+            registry.names().join(", ")
+        )
+    })
+}
+
+fn new_portfolio(config: &Config, trading: &TradingArgs) -> Result<Portfolio, String> {
+    let portfolio = Portfolio::with_cost_basis(config.portfolio.starting_balance, trading.cost_basis)
+        .with_fee_model(config.fees.clone());
+    if !trading.margin {
+        return Ok(portfolio);
+    }
+    let margin = MarginConfig {
+        initial_margin: trading.initial_margin,
+        maintenance_margin: trading.maintenance_margin,
+        borrow_rate: trading.borrow_rate,
+        ..MarginConfig::default()
+    };
+    margin.validate().map_err(|e| format!("Invalid margin settings: {}", e))?;
+    Ok(portfolio.with_margin(margin))
+}
+
+fn new_order_manager(config: &Config, trading: &TradingArgs) -> OrderManager {
+    let mut order_manager = OrderManager::new().with_slippage(config.slippage);
+    if trading.exchange_sim {
+        let liquidity = LiquidityConfig {
+            levels: trading.lp_levels,
+            level_size: trading.lp_level_size,
+            spread_bps: trading.lp_spread_bps,
+            ..LiquidityConfig::default()
+        };
+        println!(
+            "Matching against a simulated order book: {} levels of ~{} shares, {} bps spread.",
+            liquidity.levels, liquidity.level_size, liquidity.spread_bps
+        );
+        order_manager = order_manager.with_exchange(MatchingEngine::new(liquidity, config.producer.seed.unwrap_or_else(rand::random)));
+    }
+    if config.risk != RiskLimits::default() {
+        println!("Pre-trade risk limits enabled.");
+        order_manager = order_manager.with_risk(RiskEngine::new(config.risk.clone()));
+    }
+    order_manager
+}
+
+fn print_report(report: &PerformanceReport, json_path: Option<&Path>) {
+    report.display();
+    if let Some(path) = json_path {
+        match report.write_json(path) {
+            Ok(()) => println!("Performance report written to {}", path.display()),
+            Err(e) => eprintln!("[ERROR] Failed to write performance report: {}", e),
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use trades_subsystem::backtest::run_backtest_with_orders;
+use trades_subsystem::config::Config;
+use trades_subsystem::journal::{read_journal, replay_entries, replay_history, verify_snapshot};
+use trades_subsystem::metrics::PerformanceReport;
+use trades_subsystem::producer::{load_ohlcv_files, OhlcvBar};
+use trades_subsystem::snapshot::{load_snapshot, save_snapshot_at};
+use super::{create_strategy, new_order_manager, new_portfolio, print_report, CommandResult};
+use crate::{BacktestArgs, ReplayArgs, ReportArgs};
+
+pub fn backtest(config: &Config, args: &BacktestArgs) -> CommandResult {
+    let strategy = create_strategy(config)?;
+    let bars = load_ohlcv_files(&args.data).map_err(|e| format!("Failed to load backtest data: {}", e))?;
+    let portfolio = new_portfolio(config, &args.trading)?;
+    let initial_equity = portfolio.equity();
+    let orders = new_order_manager(config, &args.trading);
+    println!("Backtesting {} over {} bars.", strategy.name(), bars.len());
+
+    let result = run_backtest_with_orders(strategy.as_ref(), portfolio, orders, bars.iter().map(OhlcvBar::to_message));
+
+    println!("\nBacktest completed after {} price updates. Final Portfolio:\n", result.messages_processed);
+    result.portfolio.display_summary();
+    let open_orders: usize = result.orders.pending.values().map(|book| book.len()).sum();
+    if open_orders > 0 {
+        println!("Open orders left resting: {}", open_orders);
+    }
+    if args.trading.lot_report {
+        result.portfolio.display_realized_gains();
+    }
+    print_report(&PerformanceReport::from_backtest(&result, initial_equity), args.trading.report_json.as_deref());
+    Ok(())
+}
+
+pub fn report(args: &ReportArgs) -> CommandResult {
+    let path = &args.journal;
+    let entries = read_journal(path).map_err(|e| format!("Failed to read journal {}: {}", path.display(), e))?;
+    let history = replay_history(&entries, args.until)
+        .map_err(|e| format!("Failed to replay journal {}: {}", path.display(), e))?;
+    println!(
+        "Replayed {} fills and {} events from {}.\n",
+        history.trades.len(),
+        history.equity_curve.len(),
+        path.display()
+    );
+
+    history.portfolio.display_summary();
+    if args.lot_report {
+        history.portfolio.display_realized_gains();
+    }
+    let report = PerformanceReport::compute(history.initial_equity, &history.trades, &history.equity_curve);
+    print_report(&report, args.report_json.as_deref());
+    Ok(())
+}
+
+pub fn replay(args: &ReplayArgs) -> CommandResult {
+    let path = &args.journal;
+    let entries = read_journal(path).map_err(|e| format!("Failed to read journal {}: {}", path.display(), e))?;
+    let portfolio = replay_entries(&entries, args.until)
+        .map_err(|e| format!("Failed to replay journal {}: {}", path.display(), e))?;
+    let last_seq = entries
+        .iter()
+        .map(|entry| entry.seq)
+        .take_while(|seq| args.until.is_none_or(|until| *seq <= until))
+        .last();
+    println!("Replayed {} up to sequence {}.\n", path.display(), last_seq.unwrap_or(0));
+    portfolio.display_summary();
+
+    if let Some(snapshot_path) = &args.verify {
+        let snapshot = load_snapshot(snapshot_path)
+            .map_err(|e| format!("Failed to load snapshot {}: {}", snapshot_path.display(), e))?;
+        let differences = verify_snapshot(path, &snapshot)
+            .map_err(|e| format!("Could not verify snapshot against journal: {}", e))?;
+        if !differences.is_empty() {
+            for difference in &differences {
+                eprintln!("  {}", difference);
+            }
+            return Err(format!("Journal replay differs from snapshot {} in {} fields", snapshot_path.display(), differences.len()).into());
+        }
+        println!("Journal replay matches snapshot at sequence {:?}.", snapshot.journal_seq);
+    }
+
+    if let Some(snapshot_path) = &args.snapshot {
+        save_snapshot_at(&portfolio, last_seq, snapshot_path)
+            .map_err(|e| format!("Failed to save snapshot {}: {}", snapshot_path.display(), e))?;
+        println!("Replayed portfolio saved to {}.", snapshot_path.display());
+    }
+    Ok(())
+}
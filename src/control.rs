@@ -0,0 +1,154 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::mpsc::Sender;
+use std::thread::{self, JoinHandle};
+
+// Fanout exchange for control commands; every consumer binds its own queue, so each one gets them all.
+pub const CONTROL_EXCHANGE: &str = "trade_control";
+
+// Number of the first SIGINT/SIGTERM received, or 0.
+static SHUTDOWN_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ControlCommand {
+    HaltAll,
+    HaltSymbol(String),
+    // Lifts every halt, or only the halt on one symbol.
+    Resume(Option<String>),
+    // Cancels open orders, closes every position and halts trading.
+    FlattenAll,
+    Shutdown,
+}
+
+// One command per line: `halt [SYMBOL]`, `resume [SYMBOL]`, `flatten` or `shutdown`.
+impl FromStr for ControlCommand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let command = words.next().unwrap_or("").to_ascii_lowercase();
+        let symbol = words.next().map(|symbol| symbol.to_ascii_uppercase());
+        if words.next().is_some() {
+            return Err(format!("too many arguments in control command '{}'", s.trim()));
+        }
+        match (command.as_str(), symbol) {
+            ("halt", None) => Ok(ControlCommand::HaltAll),
+            ("halt", Some(symbol)) => Ok(ControlCommand::HaltSymbol(symbol)),
+            ("resume", symbol) => Ok(ControlCommand::Resume(symbol)),
+            ("flatten", None) => Ok(ControlCommand::FlattenAll),
+            ("shutdown", None) => Ok(ControlCommand::Shutdown),
+            _ => Err(format!(
+                "unknown control command '{}': use halt [SYMBOL], resume [SYMBOL], flatten or shutdown",
+                s.trim()
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ControlCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlCommand::HaltAll => f.write_str("halt"),
+            ControlCommand::HaltSymbol(symbol) => write!(f, "halt {}", symbol),
+            ControlCommand::Resume(None) => f.write_str("resume"),
+            ControlCommand::Resume(Some(symbol)) => write!(f, "resume {}", symbol),
+            ControlCommand::FlattenAll => f.write_str("flatten"),
+            ControlCommand::Shutdown => f.write_str("shutdown"),
+        }
+    }
+}
+
+// Accepts control connections on a Unix socket; each line is parsed, acknowledged and forwarded.
+// A stale socket file from a previous run is replaced.
+#[cfg(unix)]
+pub fn spawn_socket_listener(path: &Path, commands: Sender<ControlCommand>) -> io::Result<JoinHandle<()>> {
+    use std::os::unix::net::UnixListener;
+
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    let path: PathBuf = path.to_path_buf();
+    Ok(thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("[ERROR] Control socket {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let mut writer = match stream.try_clone() {
+                Ok(writer) => writer,
+                Err(e) => {
+                    eprintln!("[ERROR] Control socket {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let reply = match line.parse::<ControlCommand>() {
+                    Ok(command) => {
+                        let reply = format!("OK {}\n", command);
+                        if commands.send(command).is_err() {
+                            return;
+                        }
+                        reply
+                    }
+                    Err(e) => format!("ERR {}\n", e),
+                };
+                if writer.write_all(reply.as_bytes()).is_err() {
+                    break;
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(not(unix))]
+pub fn spawn_socket_listener(_path: &Path, _commands: Sender<ControlCommand>) -> io::Result<JoinHandle<()>> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "control sockets need a Unix platform"))
+}
+
+#[cfg(unix)]
+extern "C" fn on_shutdown_signal(signal: libc::c_int) {
+    SHUTDOWN_SIGNAL.store(signal, Ordering::SeqCst);
+    // A second signal falls through to the default action, so a stuck shutdown can still be killed.
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+    }
+}
+
+// Turns SIGINT and SIGTERM into a shutdown request polled through `shutdown_signal`.
+#[cfg(unix)]
+pub fn install_signal_handlers() -> io::Result<()> {
+    let handler = on_shutdown_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        if unsafe { libc::signal(signal, handler) } == libc::SIG_ERR {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn install_signal_handlers() -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "signal handlers need a Unix platform"))
+}
+
+// Name of the shutdown signal received so far, if any.
+pub fn shutdown_signal() -> Option<&'static str> {
+    match SHUTDOWN_SIGNAL.load(Ordering::SeqCst) {
+        0 => None,
+        #[cfg(unix)]
+        libc::SIGINT => Some("SIGINT"),
+        #[cfg(unix)]
+        libc::SIGTERM => Some("SIGTERM"),
+        _ => Some("signal"),
+    }
+}
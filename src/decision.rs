@@ -1,87 +1,302 @@
-use crate::portfolio::{Portfolio, FEE_RATE};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use crate::fees::Liquidity;
+use crate::journal::JournalEvent;
+use crate::models::{TradeAction, TradeMessage, TradeResponse};
+use crate::orders::{OrderManager, OrderRequest, OrderType, TimeInForce};
+use crate::portfolio::Portfolio;
 
 const DEBUG_ENABLED: bool = false;
 
-//Broker
-pub fn decide_action(
-    portfolio: &Portfolio,
-    stock_id: &str,
-    price: f64,
-    incoming_qty: u32,
-) -> (&'static str, Option<u32>) {
-    if portfolio.balance < 0.0 {
-        eprintln!("[ERROR] Invalid portfolio state: negative balance (${:.2}).", portfolio.balance);
-        return ("REFUSE", None);
+pub const DEFAULT_STRATEGY: &str = "threshold";
+pub const SHORTING_STRATEGY: &str = "threshold-short";
+pub const LIMIT_STRATEGY: &str = "threshold-limit";
+
+// How far from the quote `threshold-limit` rests its orders unless told otherwise.
+pub const DEFAULT_LIMIT_OFFSET: f64 = 0.005;
+
+//Strategy
+pub trait Strategy: Send + Sync {
+    fn name(&self) -> &str;
+    fn decide(&self, portfolio: &Portfolio, message: &TradeMessage) -> (TradeAction, Option<u32>);
+
+    // How a decision is sent to the order manager; market orders fill immediately or not at all.
+    fn order_type(&self, _action: TradeAction, _message: &TradeMessage) -> (OrderType, TimeInForce) {
+        (OrderType::Market, TimeInForce::Ioc)
     }
+}
+
+type StrategyFactory = Box<dyn Fn() -> Box<dyn Strategy> + Send + Sync>;
 
-    let last_price = portfolio.last_prices.get(stock_id).cloned().unwrap_or(price); 
-    let (quantity, avg_cost) = portfolio.holdings.get(stock_id).cloned().unwrap_or((0, 0.0));
+pub struct StrategyRegistry {
+    factories: HashMap<String, StrategyFactory>,
+}
 
-    let buy_threshold = 0.95;  
-    let max_buy_pct = 0.10;  
-    let sell_threshold_high = 1.10;  
-    let sell_threshold_low = 1.05;   
-    let stop_loss_threshold = 0.90;  
-    let trailing_stop_threshold = 0.07; 
+impl StrategyRegistry {
+    pub fn new() -> Self {
+        StrategyRegistry {
+            factories: HashMap::new(),
+        }
+    }
 
-    let max_shares_to_buy = ((portfolio.balance * max_buy_pct) / (price * (1.0 + FEE_RATE))) as u32;
+    pub fn with_defaults() -> Self {
+        StrategyRegistry::with_thresholds(ThresholdStrategy::default())
+    }
 
-    if quantity == 0 || price < last_price * buy_threshold {
-        let affordable_shares = ((portfolio.balance / (price * (1.0 + FEE_RATE))) as u32).max(0);
-        let buy_qty = affordable_shares.min(incoming_qty).min(max_shares_to_buy);  
+    // Registers the threshold family with `base` as the shared thresholds. Each name sets its own
+    // mode, so `allow_short` and `limit_offset` in `base` only reach the strategy that uses them.
+    pub fn with_thresholds(base: ThresholdStrategy) -> Self {
+        let mut registry = StrategyRegistry::new();
+        let plain = ThresholdStrategy {
+            allow_short: false,
+            limit_offset: None,
+            ..base.clone()
+        };
+        registry.register(DEFAULT_STRATEGY, move || Box::new(plain.clone()));
+        let shorting = ThresholdStrategy {
+            allow_short: true,
+            limit_offset: None,
+            ..base.clone()
+        };
+        registry.register(SHORTING_STRATEGY, move || Box::new(shorting.clone()));
+        let limit = ThresholdStrategy {
+            allow_short: false,
+            limit_offset: base.limit_offset.or(Some(DEFAULT_LIMIT_OFFSET)),
+            ..base
+        };
+        registry.register(LIMIT_STRATEGY, move || Box::new(limit.clone()));
+        registry
+    }
 
-        if buy_qty > 0 {
-            if DEBUG_ENABLED {
-                println!("[DEBUG] Buy Decision: Stock: {}, Quantity: {}, Price: {:.2}", stock_id, buy_qty, price);
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn() -> Box<dyn Strategy> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.to_string(), Box::new(factory));
+    }
+
+    pub fn create(&self, name: &str) -> Option<Box<dyn Strategy>> {
+        self.factories.get(name).map(|factory| factory())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.factories.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        names
+    }
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> Self {
+        StrategyRegistry::with_defaults()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThresholdStrategy {
+    pub buy_threshold: f64,
+    pub max_buy_pct: f64,
+    pub sell_threshold_high: f64,
+    pub sell_threshold_low: f64,
+    pub stop_loss_threshold: f64,
+    pub trailing_stop_threshold: f64,
+    // Mirror the thresholds on the short side; needs a margin account.
+    pub allow_short: bool,
+    // Rest decisions as GTC limit orders this fraction better than the quote instead of trading at market.
+    pub limit_offset: Option<f64>,
+}
+
+impl Default for ThresholdStrategy {
+    fn default() -> Self {
+        ThresholdStrategy {
+            buy_threshold: 0.95,
+            max_buy_pct: 0.10,
+            sell_threshold_high: 1.10,
+            sell_threshold_low: 1.05,
+            stop_loss_threshold: 0.90,
+            trailing_stop_threshold: 0.07,
+            allow_short: false,
+            limit_offset: None,
+        }
+    }
+}
+
+impl ThresholdStrategy {
+    pub fn validate(&self) -> Result<(), String> {
+        let fractions = [
+            ("buy_threshold", self.buy_threshold),
+            ("max_buy_pct", self.max_buy_pct),
+            ("stop_loss_threshold", self.stop_loss_threshold),
+            ("trailing_stop_threshold", self.trailing_stop_threshold),
+        ];
+        for (name, value) in fractions {
+            if !(value > 0.0 && value <= 1.0) {
+                return Err(format!("{} must be in (0, 1], got {}", name, value));
             }
-            return ("BUY", Some(buy_qty));
+        }
+        if !(self.sell_threshold_low > 1.0 && self.sell_threshold_low <= self.sell_threshold_high) {
+            return Err(format!(
+                "sell thresholds must satisfy 1 < sell_threshold_low <= sell_threshold_high, got {} and {}",
+                self.sell_threshold_low, self.sell_threshold_high
+            ));
+        }
+        if let Some(offset) = self.limit_offset {
+            if !(0.0..1.0).contains(&offset) {
+                return Err(format!("limit_offset must be in [0, 1), got {}", offset));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Strategy for ThresholdStrategy {
+    fn name(&self) -> &str {
+        if self.allow_short {
+            SHORTING_STRATEGY
+        } else if self.limit_offset.is_some() {
+            LIMIT_STRATEGY
         } else {
-            println!("[Consumer] Could not buy {} at ${:.2} due to insufficient funds.", stock_id, price);
-            return ("REFUSE", None);
+            DEFAULT_STRATEGY
         }
     }
-    else if quantity > 0 {
-        let potential_profit = (price - avg_cost) / avg_cost;
-        let mut peak_price = portfolio.last_prices.get(stock_id).cloned().unwrap_or(price);
-        peak_price = peak_price.max(price); 
 
-        let trailing_stop_price = peak_price * (1.0 - trailing_stop_threshold);
+    fn decide(&self, portfolio: &Portfolio, message: &TradeMessage) -> (TradeAction, Option<u32>) {
+        self.evaluate(portfolio, &message.stock_id, message.current_price, message.quantity)
+    }
 
-        if price > avg_cost * sell_threshold_high {
-            let sell_qty = (quantity as f64 * 0.75) as u32;
-            if DEBUG_ENABLED {
-                println!("[DEBUG] Aggressive Sell Decision: Stock: {}, Quantity: {}, Price: {:.2}, Profit: {:.2}%", stock_id, sell_qty, price, potential_profit * 100.0);
+    fn order_type(&self, action: TradeAction, message: &TradeMessage) -> (OrderType, TimeInForce) {
+        match self.limit_offset {
+            Some(offset) => {
+                let limit = match action {
+                    TradeAction::Buy | TradeAction::Cover => message.current_price * (1.0 - offset),
+                    _ => message.current_price * (1.0 + offset),
+                };
+                (OrderType::Limit { limit }, TimeInForce::Gtc)
             }
-            return ("SELL", Some(sell_qty));
+            None => (OrderType::Market, TimeInForce::Ioc),
         }
-        else if price > avg_cost * sell_threshold_low {
-            let sell_qty = (quantity as f64 * 0.50) as u32;  
-            if DEBUG_ENABLED {
-                println!("[DEBUG] Partial Sell Decision: Stock: {}, Quantity: {}, Price: {:.2}, Profit: {:.2}%", stock_id, sell_qty, price, potential_profit * 100.0);
+    }
+}
+
+impl ThresholdStrategy {
+    pub fn evaluate(
+        &self,
+        portfolio: &Portfolio,
+        stock_id: &str,
+        price: f64,
+        incoming_qty: u32,
+    ) -> (TradeAction, Option<u32>) {
+        if portfolio.margin.is_none() && portfolio.balance < 0.0 {
+            eprintln!("[ERROR] Invalid portfolio state: negative balance (${:.2}).", portfolio.balance);
+            return (TradeAction::Refuse, None);
+        }
+
+        let last_price = portfolio.last_prices.get(stock_id).cloned().unwrap_or(price);
+        let (quantity, avg_cost) = portfolio.holdings.get(stock_id).cloned().unwrap_or((0, 0.0));
+        let buying_power = portfolio.buying_power();
+
+        let max_shares_to_buy = portfolio.affordable_quantity(TradeAction::Buy, buying_power * self.max_buy_pct, price);
+
+        if let Some(position) = portfolio.shorts.get(stock_id) {
+            return self.evaluate_short(stock_id, price, position.quantity, position.avg_price);
+        }
+
+        if self.allow_short && portfolio.margin.is_some() && quantity == 0 && price > last_price / self.buy_threshold {
+            let short_qty = max_shares_to_buy.min(incoming_qty);
+            if short_qty > 0 {
+                if DEBUG_ENABLED {
+                    println!("[DEBUG] Short Decision: Stock: {}, Quantity: {}, Price: {:.2}", stock_id, short_qty, price);
+                }
+                return (TradeAction::Short, Some(short_qty));
             }
-            return ("SELL", Some(sell_qty));
         }
-        else if price < avg_cost * stop_loss_threshold {
-            if DEBUG_ENABLED {
-                println!("[DEBUG] Stop Loss Triggered: Stock: {}, Price: {:.2}, Cost: {:.2}", stock_id, price, avg_cost);
+
+        if quantity == 0 || price < last_price * self.buy_threshold {
+            let affordable_shares = portfolio.affordable_quantity(TradeAction::Buy, buying_power, price);
+            let buy_qty = affordable_shares.min(incoming_qty).min(max_shares_to_buy);
+
+            if buy_qty > 0 {
+                if DEBUG_ENABLED {
+                    println!("[DEBUG] Buy Decision: Stock: {}, Quantity: {}, Price: {:.2}", stock_id, buy_qty, price);
+                }
+                (TradeAction::Buy, Some(buy_qty))
+            } else {
+                println!("[Consumer] Could not buy {} at ${:.2} due to insufficient funds.", stock_id, price);
+                (TradeAction::Refuse, None)
+            }
+        }
+        else {
+            let potential_profit = (price - avg_cost) / avg_cost;
+            let mut peak_price = portfolio.last_prices.get(stock_id).cloned().unwrap_or(price);
+            peak_price = peak_price.max(price);
+
+            let trailing_stop_price = peak_price * (1.0 - self.trailing_stop_threshold);
+
+            if price > avg_cost * self.sell_threshold_high {
+                let sell_qty = (quantity as f64 * 0.75) as u32;
+                if DEBUG_ENABLED {
+                    println!("[DEBUG] Aggressive Sell Decision: Stock: {}, Quantity: {}, Price: {:.2}, Profit: {:.2}%", stock_id, sell_qty, price, potential_profit * 100.0);
+                }
+                (TradeAction::Sell, Some(sell_qty))
+            }
+            else if price > avg_cost * self.sell_threshold_low {
+                let sell_qty = (quantity as f64 * 0.50) as u32;
+                if DEBUG_ENABLED {
+                    println!("[DEBUG] Partial Sell Decision: Stock: {}, Quantity: {}, Price: {:.2}, Profit: {:.2}%", stock_id, sell_qty, price, potential_profit * 100.0);
+                }
+                (TradeAction::Sell, Some(sell_qty))
+            }
+            else if price < avg_cost * self.stop_loss_threshold {
+                if DEBUG_ENABLED {
+                    println!("[DEBUG] Stop Loss Triggered: Stock: {}, Price: {:.2}, Cost: {:.2}", stock_id, price, avg_cost);
+                }
+                (TradeAction::Sell, Some(quantity))
+            }
+            else if price < trailing_stop_price {
+                if DEBUG_ENABLED {
+                    println!("[DEBUG] Trailing Stop Triggered: Stock: {}, Price: {:.2}, Peak: {:.2}, Trailing Stop: {:.2}", stock_id, price, peak_price, trailing_stop_price);
+                }
+                (TradeAction::Sell, Some(quantity))
             }
-            return ("SELL", Some(quantity));  
+            else {
+                println!("[Consumer] No action taken for {} at ${:.2} because Unfavorable Price.", stock_id, price);
+                (TradeAction::Refuse, None)
+            }
+        }
+    }
+}
+
+impl ThresholdStrategy {
+    // The long-side sell rules mirrored around the short entry price.
+    fn evaluate_short(&self, stock_id: &str, price: f64, quantity: u32, avg_price: f64) -> (TradeAction, Option<u32>) {
+        if price < avg_price * (2.0 - self.sell_threshold_high) {
+            (TradeAction::Cover, Some((quantity as f64 * 0.75) as u32))
+        }
+        else if price < avg_price * (2.0 - self.sell_threshold_low) {
+            (TradeAction::Cover, Some((quantity as f64 * 0.50) as u32))
         }
-        else if price < trailing_stop_price {
+        else if price > avg_price * (2.0 - self.stop_loss_threshold) {
             if DEBUG_ENABLED {
-                println!("[DEBUG] Trailing Stop Triggered: Stock: {}, Price: {:.2}, Peak: {:.2}, Trailing Stop: {:.2}", stock_id, price, peak_price, trailing_stop_price);
+                println!("[DEBUG] Short Stop Loss Triggered: Stock: {}, Price: {:.2}, Entry: {:.2}", stock_id, price, avg_price);
             }
-            return ("SELL", Some(quantity));  
+            (TradeAction::Cover, Some(quantity))
         }
         else {
-            println!("[Consumer] No action taken for {} at ${:.2} because Unfavorable Price.", stock_id, price);
-            return ("REFUSE", None);
+            println!("[Consumer] No action taken for short {} at ${:.2} because Unfavorable Price.", stock_id, price);
+            (TradeAction::Refuse, None)
         }
     }
-    else {
-        println!("[Consumer] No action taken for {} at ${:.2} because Unfavorable Price.", stock_id, price);
-        return ("REFUSE", None);
-    }
+}
+
+//Broker
+pub fn decide_action(
+    portfolio: &Portfolio,
+    stock_id: &str,
+    price: f64,
+    incoming_qty: u32,
+) -> (TradeAction, Option<u32>) {
+    ThresholdStrategy::default().evaluate(portfolio, stock_id, price, incoming_qty)
 }
 
 //Order Manager
@@ -89,8 +304,9 @@ pub fn execute_trade_action(
     portfolio: &mut Portfolio,
     stock_id: &str,
     price: f64,
-    action: &str,
+    action: TradeAction,
     final_quantity: Option<u32>,
+    liquidity: Liquidity,
 ) -> Option<u32> {
     if let Some(quantity) = final_quantity {
         if DEBUG_ENABLED {
@@ -101,11 +317,11 @@ pub fn execute_trade_action(
         }
 
         match action {
-            "BUY" => {
-                let cost = price * quantity as f64 * (1.0 + FEE_RATE);
-                if portfolio.balance >= cost {
-                    portfolio.update(stock_id, quantity, price, action);
-                    Some(quantity)
+            TradeAction::Buy => {
+                let cost = price * quantity as f64 + portfolio.fee_for(action, quantity, price, liquidity);
+                if portfolio.buying_power() >= cost {
+                    portfolio.record(JournalEvent::OrderAccepted { stock_id: stock_id.to_string(), action, quantity, price });
+                    portfolio.update_with_liquidity(stock_id, quantity, price, action, liquidity).then_some(quantity)
                 } else {
                     println!(
                         "[Order Manager] Consumer don't have insufficient funds to complete BUY of {} shares of {}.",
@@ -114,10 +330,10 @@ pub fn execute_trade_action(
                     None
                 }
             }
-            "SELL" => {
+            TradeAction::Sell => {
                 if portfolio.get_stock_quantity(stock_id) >= quantity {
-                    portfolio.update(stock_id, quantity, price, action);
-                    Some(quantity)
+                    portfolio.record(JournalEvent::OrderAccepted { stock_id: stock_id.to_string(), action, quantity, price });
+                    portfolio.update_with_liquidity(stock_id, quantity, price, action, liquidity).then_some(quantity)
                 } else {
                     println!(
                         "[Order Manager] Consumer do not have enough shares to SELL {} shares of {}.",
@@ -126,10 +342,31 @@ pub fn execute_trade_action(
                     None
                 }
             }
-            _ => {
-                println!("[ERROR] Unknown action: {}. No trade executed.", action);
-                None
+            TradeAction::Short => {
+                if portfolio.margin.is_some() && portfolio.get_stock_quantity(stock_id) == 0 {
+                    portfolio.record(JournalEvent::OrderAccepted { stock_id: stock_id.to_string(), action, quantity, price });
+                    portfolio.update_with_liquidity(stock_id, quantity, price, action, liquidity).then_some(quantity)
+                } else {
+                    println!(
+                        "[Order Manager] Consumer cannot SHORT {} shares of {}: requires a margin account and no long position.",
+                        quantity, stock_id
+                    );
+                    None
+                }
+            }
+            TradeAction::Cover => {
+                if portfolio.short_quantity(stock_id) >= quantity {
+                    portfolio.record(JournalEvent::OrderAccepted { stock_id: stock_id.to_string(), action, quantity, price });
+                    portfolio.update_with_liquidity(stock_id, quantity, price, action, liquidity).then_some(quantity)
+                } else {
+                    println!(
+                        "[Order Manager] Consumer do not have enough short shares to COVER {} shares of {}.",
+                        quantity, stock_id
+                    );
+                    None
+                }
             }
+            TradeAction::Refuse => None,
         }
     } else {
         if DEBUG_ENABLED {
@@ -142,12 +379,183 @@ pub fn execute_trade_action(
     }
 }
 
-/*
-#[test]
-fn test_decide_action_buy() {
-    let mut portfolio = Portfolio::new(10000.0);
-    portfolio.update_last_price("AAPL", 160.0);
-    let action = decide_action(&portfolio, "AAPL", 150.0, 100);
-    assert_eq!(action.0, "BUY");
+// Runs one price update through the order book and strategy, records the new last price, then
+// liquidates if that price leaves the account short of margin. Resting orders are filled first,
+// so the response list holds their fills ahead of the new decision and any forced liquidation.
+pub fn handle_trade_message(
+    strategy: &dyn Strategy,
+    portfolio: &mut Portfolio,
+    orders: &mut OrderManager,
+    message: &TradeMessage,
+    timestamp: String,
+) -> Vec<TradeResponse> {
+    portfolio.advance_clock(&message.timestamp);
+
+    let mut responses = orders.on_price_update(portfolio, message, &timestamp);
+    let message = &orders.quote(message);
+
+    // No new positions while the account is short of margin; it is being liquidated instead.
+    let (action, final_quantity) = if portfolio.margin_deficit().is_some() {
+        (TradeAction::Refuse, None)
+    } else {
+        strategy.decide(portfolio, message)
+    };
+    match final_quantity.filter(|quantity| *quantity > 0 && action != TradeAction::Refuse) {
+        Some(quantity) => {
+            let (order_type, time_in_force) = strategy.order_type(action, message);
+            if order_type != OrderType::Market {
+                // A fresh decision replaces whatever is still resting on the same side of this symbol.
+                let stale: Vec<u64> = orders
+                    .open_orders(&message.stock_id)
+                    .iter()
+                    .filter(|order| order.action == action)
+                    .map(|order| order.id)
+                    .collect();
+                for order_id in stale {
+                    orders.cancel(order_id);
+                }
+            }
+            let request = OrderRequest {
+                order_type,
+                time_in_force,
+                ..OrderRequest::market(&message.stock_id, action, quantity)
+            };
+            responses.push(orders.submit(portfolio, request, message, &timestamp));
+        }
+        None => responses.push(TradeResponse {
+            stock_id: message.stock_id.clone(),
+            decision: action,
+            quantity: 0,
+            price: message.current_price,
+            quoted_price: message.current_price,
+            fee: 0.0,
+            timestamp: timestamp.clone(),
+            order_id: None,
+            order_status: None,
+            rejection: None,
+        }),
+    }
+
+    portfolio.update_last_price(&message.stock_id, message.current_price);
+
+    // Margin is checked on marks that include this update. Resting orders are pulled first so
+    // none of them can reopen what the liquidation closes.
+    if let Some(deficit) = portfolio.margin_deficit() {
+        if let Some((stock_id, action, quantity, price)) = portfolio.liquidation_order() {
+            responses.extend(orders.cancel_all(portfolio, &timestamp));
+            println!(
+                "[Margin] Margin call (deficit ${:.2}): forced {} of {} shares of {} at ${:.2}.",
+                deficit, action, quantity, stock_id, price
+            );
+            let fees_before = portfolio.total_fees;
+            let executed_quantity = execute_trade_action(portfolio, &stock_id, price, action, Some(quantity), Liquidity::Taker);
+            let fee = portfolio.total_fees - fees_before;
+            responses.push(TradeResponse {
+                stock_id,
+                decision: action,
+                quantity: executed_quantity.unwrap_or(0),
+                price,
+                quoted_price: price,
+                fee,
+                timestamp,
+                order_id: None,
+                order_status: None,
+                rejection: None,
+            });
+        }
+    }
+    responses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::margin::MarginConfig;
+    use crate::models::MessageType;
+    use crate::orders::OrderStatus;
+
+    // Never trades, so anything that happens comes from the order book or the margin check.
+    struct Hold;
+
+    impl Strategy for Hold {
+        fn name(&self) -> &str {
+            "hold"
+        }
+
+        fn decide(&self, _portfolio: &Portfolio, _message: &TradeMessage) -> (TradeAction, Option<u32>) {
+            (TradeAction::Refuse, None)
+        }
+    }
+
+    fn tick(stock_id: &str, price: f64, timestamp: &str) -> TradeMessage {
+        TradeMessage {
+            stock_id: stock_id.to_string(),
+            current_price: price,
+            action_type: MessageType::PriceUpdate,
+            quantity: 10,
+            timestamp: timestamp.to_string(),
+        }
+    }
+
+    #[test]
+    fn buys_after_a_drop_below_the_buy_threshold() {
+        let mut portfolio = Portfolio::new(10000.0);
+        portfolio.update_last_price("AAPL", 160.0);
+        let (action, quantity) = ThresholdStrategy::default().evaluate(&portfolio, "AAPL", 150.0, 100);
+        assert_eq!(action, TradeAction::Buy);
+        assert!(quantity.is_some_and(|quantity| quantity > 0));
+    }
+
+    #[test]
+    fn margin_call_liquidates_on_the_tick_that_causes_it() {
+        let mut portfolio = Portfolio::new(10000.0).with_margin(MarginConfig::default());
+        let mut orders = OrderManager::new();
+        let at = "2024-01-02T15:00:00Z";
+
+        portfolio.update_last_price("AAPL", 100.0);
+        portfolio.update_last_price("TSLA", 100.0);
+        portfolio.update("TSLA", 100, 100.0, TradeAction::Short);
+        // Rests well below the market.
+        let resting = OrderRequest {
+            order_type: OrderType::Limit { limit: 50.0 },
+            time_in_force: TimeInForce::Gtc,
+            ..OrderRequest::market("AAPL", TradeAction::Buy, 5)
+        };
+        let response = orders.submit(&mut portfolio, resting, &tick("AAPL", 100.0, at), at);
+        let resting_id = response.order_id.unwrap();
+        assert_eq!(orders.open_orders("AAPL").len(), 1);
+
+        // The spike alone puts the short under maintenance margin.
+        let responses = handle_trade_message(&Hold, &mut portfolio, &mut orders, &tick("TSLA", 170.0, at), at.to_string());
+
+        assert_eq!(portfolio.last_prices["TSLA"], 170.0);
+        assert_eq!(portfolio.short_quantity("TSLA"), 0);
+        let cover = responses.iter().find(|r| r.decision == TradeAction::Cover).expect("forced cover");
+        assert_eq!((cover.quantity, cover.price), (100, 170.0));
+        let cancelled = responses.iter().position(|r| r.order_id == Some(resting_id)).expect("cancelled order");
+        assert_eq!(responses[cancelled].order_status, Some(OrderStatus::Cancelled));
+        assert!(cancelled < responses.iter().position(|r| r.decision == TradeAction::Cover).unwrap());
+        assert!(orders.open_orders("AAPL").is_empty());
+    }
+
+    #[test]
+    fn each_threshold_strategy_sets_its_own_mode() {
+        let base = ThresholdStrategy {
+            buy_threshold: 0.9,
+            allow_short: true,
+            limit_offset: Some(0.01),
+            ..ThresholdStrategy::default()
+        };
+        let registry = StrategyRegistry::with_thresholds(base);
+        let message = tick("AAPL", 100.0, "2024-01-02T15:00:00Z");
+        for (name, order_type) in [
+            (DEFAULT_STRATEGY, OrderType::Market),
+            (SHORTING_STRATEGY, OrderType::Market),
+            (LIMIT_STRATEGY, OrderType::Limit { limit: 99.0 }),
+        ] {
+            let strategy = registry.create(name).unwrap();
+            assert_eq!(strategy.name(), name);
+            assert_eq!(strategy.order_type(TradeAction::Buy, &message).0, order_type);
+        }
+    }
 }
-*/
\ No newline at end of file
@@ -0,0 +1,358 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use crate::models::{TradeAction, TradeMessage};
+
+pub const TICK_SIZE: f64 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+impl Side {
+    pub fn for_action(action: TradeAction) -> Side {
+        match action {
+            TradeAction::Buy | TradeAction::Cover => Side::Bid,
+            _ => Side::Ask,
+        }
+    }
+
+    pub fn opposite(&self) -> Side {
+        match self {
+            Side::Bid => Side::Ask,
+            Side::Ask => Side::Bid,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Participant {
+    // One of our orders, keyed by its order manager ID.
+    Client(u64),
+    Provider,
+    Flow,
+}
+
+#[derive(Debug, Clone)]
+struct RestingOrder {
+    owner: Participant,
+    quantity: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trade {
+    pub maker: Participant,
+    pub taker: Participant,
+    pub price: f64,
+    pub quantity: u32,
+}
+
+// A fill on one of our orders, as maker (resting) or taker (crossing the spread).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fill {
+    pub order_id: u64,
+    pub price: f64,
+    pub quantity: u32,
+    pub maker: bool,
+}
+
+fn to_ticks(price: f64, side: Side) -> i64 {
+    // Bids round down and asks round up so a limit is never made more aggressive.
+    match side {
+        Side::Bid => (price / TICK_SIZE + 1e-9).floor() as i64,
+        Side::Ask => (price / TICK_SIZE - 1e-9).ceil() as i64,
+    }
+}
+
+fn to_price(ticks: i64) -> f64 {
+    ticks as f64 / (1.0 / TICK_SIZE).round()
+}
+
+// Price levels in ticks; each level is a FIFO queue, which gives price-time priority.
+#[derive(Debug, Clone, Default)]
+pub struct OrderBook {
+    bids: BTreeMap<i64, VecDeque<RestingOrder>>,
+    asks: BTreeMap<i64, VecDeque<RestingOrder>>,
+}
+
+impl OrderBook {
+    pub fn new() -> Self {
+        OrderBook::default()
+    }
+
+    fn side(&self, side: Side) -> &BTreeMap<i64, VecDeque<RestingOrder>> {
+        match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        }
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut BTreeMap<i64, VecDeque<RestingOrder>> {
+        match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<f64> {
+        self.bids.keys().next_back().map(|ticks| to_price(*ticks))
+    }
+
+    pub fn best_ask(&self) -> Option<f64> {
+        self.asks.keys().next().map(|ticks| to_price(*ticks))
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.best_bid()? + self.best_ask()?) / 2.0)
+    }
+
+    // Best-first (price, quantity) levels on one side, at most `levels` of them.
+    pub fn depth(&self, side: Side, levels: usize) -> Vec<(f64, u32)> {
+        let level = |(ticks, queue): (&i64, &VecDeque<RestingOrder>)| {
+            (to_price(*ticks), queue.iter().map(|order| order.quantity).sum())
+        };
+        match side {
+            Side::Bid => self.bids.iter().rev().take(levels).map(level).collect(),
+            Side::Ask => self.asks.iter().take(levels).map(level).collect(),
+        }
+    }
+
+    pub fn displayed_quantity(&self, side: Side) -> u32 {
+        self.side(side).values().flatten().map(|order| order.quantity).sum()
+    }
+
+    // Quantity an aggressor on `side` could take from other participants without crossing `limit`.
+    pub fn available(&self, side: Side, limit: Option<f64>) -> u32 {
+        let limit = limit.map(|price| to_ticks(price, side));
+        self.side(side.opposite())
+            .iter()
+            .filter(|(ticks, _)| match (side, limit) {
+                (_, None) => true,
+                (Side::Bid, Some(limit)) => **ticks <= limit,
+                (Side::Ask, Some(limit)) => **ticks >= limit,
+            })
+            .flat_map(|(_, queue)| queue.iter())
+            .filter(|order| !matches!(order.owner, Participant::Client(_)))
+            .map(|order| order.quantity)
+            .sum()
+    }
+
+    // Shares queued ahead of the order at its price level.
+    pub fn queue_position(&self, owner: Participant) -> Option<u32> {
+        for queue in self.bids.values().chain(self.asks.values()) {
+            if let Some(index) = queue.iter().position(|order| order.owner == owner) {
+                return Some(queue.iter().take(index).map(|order| order.quantity).sum());
+            }
+        }
+        None
+    }
+
+    pub fn cancel(&mut self, owner: Participant) -> u32 {
+        let mut cancelled = 0;
+        for book in [&mut self.bids, &mut self.asks] {
+            for queue in book.values_mut() {
+                queue.retain(|order| {
+                    let matches = order.owner == owner;
+                    if matches {
+                        cancelled += order.quantity;
+                    }
+                    !matches
+                });
+            }
+            book.retain(|_, queue| !queue.is_empty());
+        }
+        cancelled
+    }
+
+    // Matches an incoming order against the opposite side, then rests any remainder when asked to.
+    // Client orders never trade with each other; they are skipped rather than crossed.
+    pub fn submit(&mut self, owner: Participant, side: Side, limit: Option<f64>, quantity: u32, rest: bool) -> Vec<Trade> {
+        let limit_ticks = limit.map(|price| to_ticks(price, side));
+        let crosses = |ticks: i64| match (side, limit_ticks) {
+            (_, None) => true,
+            (Side::Bid, Some(limit)) => ticks <= limit,
+            (Side::Ask, Some(limit)) => ticks >= limit,
+        };
+
+        let mut remaining = quantity;
+        let mut trades = Vec::new();
+        let levels: Vec<i64> = match side {
+            Side::Bid => self.asks.keys().cloned().collect(),
+            Side::Ask => self.bids.keys().rev().cloned().collect(),
+        };
+        let opposite = self.side_mut(side.opposite());
+        for ticks in levels {
+            if remaining == 0 || !crosses(ticks) {
+                break;
+            }
+            let Some(queue) = opposite.get_mut(&ticks) else { continue };
+            for resting in queue.iter_mut() {
+                if remaining == 0 {
+                    break;
+                }
+                let self_trade = matches!((resting.owner, owner), (Participant::Client(_), Participant::Client(_)));
+                if self_trade || resting.quantity == 0 {
+                    continue;
+                }
+                let quantity = resting.quantity.min(remaining);
+                resting.quantity -= quantity;
+                remaining -= quantity;
+                trades.push(Trade {
+                    maker: resting.owner,
+                    taker: owner,
+                    price: to_price(ticks),
+                    quantity,
+                });
+            }
+            queue.retain(|order| order.quantity > 0);
+            if queue.is_empty() {
+                opposite.remove(&ticks);
+            }
+        }
+
+        if rest && remaining > 0 {
+            if let Some(ticks) = limit_ticks {
+                self.side_mut(side)
+                    .entry(ticks)
+                    .or_default()
+                    .push_back(RestingOrder { owner, quantity: remaining });
+            }
+        }
+        trades
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LiquidityConfig {
+    // Price levels posted on each side of the quote.
+    pub levels: usize,
+    // Distance between the best bid and best ask, in basis points of the quote.
+    pub spread_bps: f64,
+    // Distance between consecutive levels, in basis points of the quote.
+    pub level_spacing_bps: f64,
+    // Average shares posted per level; actual sizes vary by +/-50%.
+    pub level_size: u32,
+}
+
+impl Default for LiquidityConfig {
+    fn default() -> Self {
+        LiquidityConfig {
+            levels: 5,
+            spread_bps: 10.0,
+            level_spacing_bps: 5.0,
+            level_size: 100,
+        }
+    }
+}
+
+// Local exchange simulator: one book per symbol, refreshed by synthetic liquidity providers on every price update.
+#[derive(Debug, Clone)]
+pub struct MatchingEngine {
+    books: HashMap<String, OrderBook>,
+    liquidity: LiquidityConfig,
+    rng: StdRng,
+}
+
+impl MatchingEngine {
+    pub fn new(liquidity: LiquidityConfig, seed: u64) -> Self {
+        MatchingEngine {
+            books: HashMap::new(),
+            liquidity,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn book(&self, stock_id: &str) -> Option<&OrderBook> {
+        self.books.get(stock_id)
+    }
+
+    pub fn liquidity(&self) -> &LiquidityConfig {
+        &self.liquidity
+    }
+
+    // Providers pull their old quotes and re-post around the new price; the message quantity then
+    // trades as anonymous market flow. Returns the fills this produced on our resting orders.
+    pub fn on_price_update(&mut self, message: &TradeMessage) -> Vec<Fill> {
+        let book = self.books.entry(message.stock_id.clone()).or_default();
+        book.cancel(Participant::Provider);
+
+        let price = message.current_price;
+        let half_spread = self.liquidity.spread_bps / 2.0 / 10_000.0;
+        let spacing = self.liquidity.level_spacing_bps / 10_000.0;
+        let mut trades = Vec::new();
+        for level in 0..self.liquidity.levels {
+            let offset = half_spread + spacing * level as f64;
+            for (side, level_price) in [(Side::Bid, price * (1.0 - offset)), (Side::Ask, price * (1.0 + offset))] {
+                let size = (self.liquidity.level_size as f64 * self.rng.gen_range(0.5..1.5)).round().max(1.0) as u32;
+                trades.extend(book.submit(Participant::Provider, side, Some(level_price), size, true));
+            }
+        }
+
+        if message.quantity > 0 {
+            let side = if self.rng.gen_bool(0.5) { Side::Bid } else { Side::Ask };
+            trades.extend(book.submit(Participant::Flow, side, None, message.quantity, false));
+        }
+
+        trades
+            .into_iter()
+            .filter_map(|trade| match trade.maker {
+                Participant::Client(order_id) => Some(Fill {
+                    order_id,
+                    price: trade.price,
+                    quantity: trade.quantity,
+                    maker: true,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // Sends one of our orders to the book: it takes liquidity up to `limit` and optionally rests the remainder.
+    pub fn submit(
+        &mut self,
+        order_id: u64,
+        stock_id: &str,
+        action: TradeAction,
+        quantity: u32,
+        limit: Option<f64>,
+        rest: bool,
+    ) -> Vec<Fill> {
+        let book = self.books.entry(stock_id.to_string()).or_default();
+        book.submit(Participant::Client(order_id), Side::for_action(action), limit, quantity, rest)
+            .into_iter()
+            .map(|trade| Fill {
+                order_id,
+                price: trade.price,
+                quantity: trade.quantity,
+                maker: false,
+            })
+            .collect()
+    }
+
+    pub fn available(&self, stock_id: &str, action: TradeAction, limit: Option<f64>) -> u32 {
+        self.books
+            .get(stock_id)
+            .map(|book| book.available(Side::for_action(action), limit))
+            .unwrap_or(0)
+    }
+
+    pub fn cancel(&mut self, stock_id: &str, order_id: u64) {
+        if let Some(book) = self.books.get_mut(stock_id) {
+            book.cancel(Participant::Client(order_id));
+        }
+    }
+
+    pub fn queue_position(&self, stock_id: &str, order_id: u64) -> Option<u32> {
+        self.books.get(stock_id)?.queue_position(Participant::Client(order_id))
+    }
+
+    // Size the strategy sees as "quoted": the thinner side of the displayed book.
+    pub fn displayed_depth(&self, stock_id: &str) -> u32 {
+        self.books
+            .get(stock_id)
+            .map(|book| book.displayed_quantity(Side::Bid).min(book.displayed_quantity(Side::Ask)))
+            .unwrap_or(0)
+    }
+}
@@ -0,0 +1,265 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use crate::models::TradeAction;
+
+pub const DEFAULT_FEE_RATE: f64 = 0.001;
+
+// US-style regulatory fees: a SEC fee on sale proceeds and a FINRA TAF per share sold.
+const SEC_FEE_RATE: f64 = 0.0000278;
+const TAF_PER_SHARE: f64 = 0.000166;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Liquidity {
+    // Rested in the book and was hit by someone else.
+    Maker,
+    // Crossed the spread, or filled at the quote straight away.
+    #[default]
+    Taker,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeeTier {
+    // Notional traded so far this calendar month at which this rate starts to apply.
+    pub min_monthly_volume: f64,
+    pub rate: f64,
+}
+
+// Written as `{ "type": "PER_SHARE", "per_share": 0.005 }` in JSON and TOML. Compact formats such
+// as the binary snapshot can't read internally tagged enums, so they get `CompactCommission`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Commission {
+    Percentage { rate: f64 },
+    PerShare { per_share: f64 },
+    Tiered { tiers: Vec<FeeTier> },
+    // A negative maker rate is a rebate.
+    MakerTaker { maker_rate: f64, taker_rate: f64 },
+}
+
+// Externally tagged twin of `Commission`, used for formats that aren't human-readable.
+#[derive(Serialize, Deserialize)]
+enum CompactCommission {
+    Percentage { rate: f64 },
+    PerShare { per_share: f64 },
+    Tiered { tiers: Vec<FeeTier> },
+    MakerTaker { maker_rate: f64, taker_rate: f64 },
+}
+
+impl From<Commission> for CompactCommission {
+    fn from(commission: Commission) -> Self {
+        match commission {
+            Commission::Percentage { rate } => CompactCommission::Percentage { rate },
+            Commission::PerShare { per_share } => CompactCommission::PerShare { per_share },
+            Commission::Tiered { tiers } => CompactCommission::Tiered { tiers },
+            Commission::MakerTaker { maker_rate, taker_rate } => CompactCommission::MakerTaker { maker_rate, taker_rate },
+        }
+    }
+}
+
+impl From<CompactCommission> for Commission {
+    fn from(commission: CompactCommission) -> Self {
+        match commission {
+            CompactCommission::Percentage { rate } => Commission::Percentage { rate },
+            CompactCommission::PerShare { per_share } => Commission::PerShare { per_share },
+            CompactCommission::Tiered { tiers } => Commission::Tiered { tiers },
+            CompactCommission::MakerTaker { maker_rate, taker_rate } => Commission::MakerTaker { maker_rate, taker_rate },
+        }
+    }
+}
+
+fn serialize_commission<S: Serializer>(commission: &Commission, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        commission.serialize(serializer)
+    } else {
+        CompactCommission::from(commission.clone()).serialize(serializer)
+    }
+}
+
+fn deserialize_commission<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Commission, D::Error> {
+    if deserializer.is_human_readable() {
+        Commission::deserialize(deserializer)
+    } else {
+        CompactCommission::deserialize(deserializer).map(Commission::from)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeeModel {
+    #[serde(serialize_with = "serialize_commission", deserialize_with = "deserialize_commission")]
+    pub commission: Commission,
+    // Smallest commission charged on any fill.
+    #[serde(default)]
+    pub minimum_ticket: f64,
+    // Charged on sell-side notional (SELL and SHORT) only.
+    #[serde(default)]
+    pub regulatory_rate: f64,
+    // Charged per share sold (SELL and SHORT) only.
+    #[serde(default)]
+    pub regulatory_per_share: f64,
+}
+
+impl Default for FeeModel {
+    fn default() -> Self {
+        FeeModel::percentage(DEFAULT_FEE_RATE)
+    }
+}
+
+impl FeeModel {
+    pub fn percentage(rate: f64) -> Self {
+        FeeModel {
+            commission: Commission::Percentage { rate },
+            minimum_ticket: 0.0,
+            regulatory_rate: 0.0,
+            regulatory_per_share: 0.0,
+        }
+    }
+
+    pub fn with_regulatory_fees(mut self) -> Self {
+        self.regulatory_rate = SEC_FEE_RATE;
+        self.regulatory_per_share = TAF_PER_SHARE;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.minimum_ticket < 0.0 || self.regulatory_rate < 0.0 || self.regulatory_per_share < 0.0 {
+            return Err("minimum ticket and regulatory fees must be non-negative".to_string());
+        }
+        match &self.commission {
+            Commission::Percentage { rate } if *rate < 0.0 => Err(format!("percentage rate must be non-negative, got {}", rate)),
+            Commission::PerShare { per_share } if *per_share < 0.0 => {
+                Err(format!("per-share fee must be non-negative, got {}", per_share))
+            }
+            Commission::Tiered { tiers } if tiers.is_empty() => Err("tiered schedule needs at least one tier".to_string()),
+            Commission::Tiered { tiers } if tiers.iter().any(|tier| tier.rate < 0.0 || tier.min_monthly_volume < 0.0) => {
+                Err("tier rates and volumes must be non-negative".to_string())
+            }
+            Commission::MakerTaker { maker_rate, taker_rate } if *taker_rate < 0.0 || maker_rate + taker_rate < 0.0 => {
+                Err("taker rate must be non-negative and the maker rebate cannot exceed it".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn commission(&self, quantity: u32, price: f64, liquidity: Liquidity, monthly_volume: f64) -> f64 {
+        let notional = price * quantity as f64;
+        let commission = match &self.commission {
+            Commission::Percentage { rate } => rate * notional,
+            Commission::PerShare { per_share } => per_share * quantity as f64,
+            Commission::Tiered { tiers } => {
+                let rate = tiers
+                    .iter()
+                    .filter(|tier| monthly_volume >= tier.min_monthly_volume)
+                    .max_by(|a, b| a.min_monthly_volume.total_cmp(&b.min_monthly_volume))
+                    .map(|tier| tier.rate)
+                    .unwrap_or(0.0);
+                rate * notional
+            }
+            Commission::MakerTaker { maker_rate, taker_rate } => match liquidity {
+                Liquidity::Maker => maker_rate * notional,
+                Liquidity::Taker => taker_rate * notional,
+            },
+        };
+        // Rebates are passed through as-is; only a positive charge is lifted to the minimum ticket.
+        if commission > 0.0 {
+            commission.max(self.minimum_ticket)
+        } else {
+            commission
+        }
+    }
+
+    pub fn regulatory(&self, action: TradeAction, quantity: u32, price: f64) -> f64 {
+        match action {
+            TradeAction::Sell | TradeAction::Short => {
+                self.regulatory_rate * price * quantity as f64 + self.regulatory_per_share * quantity as f64
+            }
+            _ => 0.0,
+        }
+    }
+
+    // Total fee for one fill; negative only when a maker rebate outweighs everything else.
+    pub fn fee(&self, action: TradeAction, quantity: u32, price: f64, liquidity: Liquidity, monthly_volume: f64) -> f64 {
+        if quantity == 0 {
+            return 0.0;
+        }
+        self.commission(quantity, price, liquidity, monthly_volume) + self.regulatory(action, quantity, price)
+    }
+
+    // Largest quantity whose notional plus worst-case (taker) fee fits in `budget`.
+    pub fn affordable_quantity(&self, action: TradeAction, budget: f64, price: f64, monthly_volume: f64) -> u32 {
+        if budget <= 0.0 || price <= 0.0 {
+            return 0;
+        }
+        let cost = |quantity: u32| price * quantity as f64 + self.fee(action, quantity, price, Liquidity::Taker, monthly_volume).max(0.0);
+        let (mut low, mut high) = (0u32, (budget / price).min(u32::MAX as f64) as u32);
+        while low < high {
+            let mid = low + (high - low).div_ceil(2);
+            if cost(mid) <= budget {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        low
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        let model = match name.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "flat" | "percentage" => FeeModel::default(),
+            "per-share" => FeeModel {
+                commission: Commission::PerShare { per_share: 0.005 },
+                minimum_ticket: 1.0,
+                ..FeeModel::percentage(0.0)
+            }
+            .with_regulatory_fees(),
+            "tiered" => FeeModel {
+                commission: Commission::Tiered {
+                    tiers: vec![
+                        FeeTier { min_monthly_volume: 0.0, rate: 0.0010 },
+                        FeeTier { min_monthly_volume: 1_000_000.0, rate: 0.0008 },
+                        FeeTier { min_monthly_volume: 10_000_000.0, rate: 0.0005 },
+                    ],
+                },
+                ..FeeModel::percentage(0.0)
+            }
+            .with_regulatory_fees(),
+            "maker-taker" => FeeModel {
+                commission: Commission::MakerTaker { maker_rate: -0.0002, taker_rate: 0.0003 },
+                ..FeeModel::percentage(0.0)
+            }
+            .with_regulatory_fees(),
+            _ => return None,
+        };
+        Some(model)
+    }
+}
+
+pub fn load_fee_model<P: AsRef<Path>>(path: P) -> io::Result<FeeModel> {
+    let model: FeeModel = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    model
+        .validate()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(model)
+}
+
+// Either a preset name or the path of a JSON fee schedule.
+impl FromStr for FeeModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(model) = FeeModel::preset(s) {
+            return Ok(model);
+        }
+        if s.to_ascii_lowercase().ends_with(".json") {
+            return load_fee_model(s).map_err(|e| format!("failed to load fee schedule {}: {}", s, e));
+        }
+        Err(format!(
+            "unknown fee model '{}': use flat, per-share, tiered, maker-taker or a .json schedule",
+            s
+        ))
+    }
+}
@@ -0,0 +1,488 @@
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use crate::backtest::EquityPoint;
+use crate::fees::{FeeModel, Liquidity};
+use crate::lots::CostBasisMethod;
+use crate::margin::MarginConfig;
+use crate::models::{TradeAction, TradeResponse};
+use crate::portfolio::Portfolio;
+use crate::snapshot::Snapshot;
+
+const EPSILON: f64 = 1e-6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsyncPolicy {
+    Always,
+    EveryN(u32),
+    Never,
+}
+
+impl FromStr for FsyncPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "always" => Ok(FsyncPolicy::Always),
+            "never" => Ok(FsyncPolicy::Never),
+            other => other
+                .strip_prefix("every:")
+                .and_then(|n| n.parse().ok())
+                .filter(|n| *n > 0)
+                .map(FsyncPolicy::EveryN)
+                .ok_or_else(|| format!("unknown fsync policy '{}': use always, never or every:N", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum JournalEvent {
+    Opened {
+        initial_balance: f64,
+        cost_basis_method: CostBasisMethod,
+        #[serde(default)]
+        margin: Option<MarginConfig>,
+        #[serde(default)]
+        fee_model: FeeModel,
+    },
+    // Opens a journal for a portfolio that already has history, e.g. one resumed from a snapshot.
+    Checkpoint {
+        portfolio: Box<Portfolio>,
+    },
+    OrderAccepted {
+        stock_id: String,
+        action: TradeAction,
+        quantity: u32,
+        price: f64,
+    },
+    Fill {
+        stock_id: String,
+        action: TradeAction,
+        quantity: u32,
+        price: f64,
+        #[serde(default)]
+        liquidity: Liquidity,
+    },
+    PriceUpdate {
+        stock_id: String,
+        price: f64,
+    },
+    CashMovement {
+        amount: f64,
+        reason: String,
+    },
+    FinancingCharge {
+        stock_id: String,
+        amount: f64,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub event: JournalEvent,
+}
+
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    policy: FsyncPolicy,
+    last_seq: u64,
+    unsynced: u32,
+}
+
+impl Journal {
+    // Opens (or creates) the journal for appending and continues numbering after its last entry.
+    pub fn open<P: AsRef<Path>>(path: P, policy: FsyncPolicy) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (entries, file) = open_json_lines::<JournalEntry, _>(&path, "journal")?;
+        let last_seq = entries.last().map(|entry| entry.seq).unwrap_or(0);
+        Ok(Journal {
+            path,
+            writer: BufWriter::new(file),
+            policy,
+            last_seq,
+            unsynced: 0,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.last_seq == 0
+    }
+
+    pub fn append(&mut self, timestamp: DateTime<Utc>, event: &JournalEvent) -> io::Result<u64> {
+        let entry = JournalEntry {
+            seq: self.last_seq + 1,
+            timestamp,
+            event: event.clone(),
+        };
+        let line = serde_json::to_string(&entry).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.writer.write_all(line.as_bytes())?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+
+        self.unsynced += 1;
+        let should_sync = match self.policy {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::EveryN(n) => self.unsynced >= n,
+            FsyncPolicy::Never => false,
+        };
+        if should_sync {
+            self.sync()?;
+        }
+
+        self.last_seq = entry.seq;
+        Ok(entry.seq)
+    }
+
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_data()?;
+        self.unsynced = 0;
+        Ok(())
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        if let Err(e) = self.sync() {
+            eprintln!("[ERROR] Failed to sync journal {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+pub fn read_journal<P: AsRef<Path>>(path: P) -> io::Result<Vec<JournalEntry>> {
+    read_json_lines(path, "journal")
+}
+
+// Loads an append-only JSON-lines file of `what` entries (creating it if missing) and opens it for
+// appending. A torn final line is cut off first, so the next entry starts on a line of its own
+// instead of being glued to the fragment.
+pub fn open_json_lines<T: DeserializeOwned, P: AsRef<Path>>(path: P, what: &str) -> io::Result<(Vec<T>, File)> {
+    let path = path.as_ref();
+    if !path.exists() {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        return Ok((Vec::new(), file));
+    }
+    let entries = read_json_lines(path, what)?;
+    let bytes = fs::read(path)?;
+    let file = OpenOptions::new().append(true).open(path)?;
+    if bytes.last().is_some_and(|byte| *byte != b'\n') {
+        let end = bytes.iter().rposition(|byte| *byte == b'\n').map_or(0, |index| index + 1);
+        if serde_json::from_slice::<serde_json::Value>(&bytes[end..]).is_ok() {
+            // Complete entry that only lost its newline; read_json_lines kept it.
+            (&file).write_all(b"\n")?;
+        } else {
+            eprintln!("[WARN] Truncating incomplete last {} entry in {} at byte {}", what, path.display(), end);
+            file.set_len(end as u64)?;
+        }
+    }
+    Ok((entries, file))
+}
+
+// Reads an append-only JSON-lines file of `what` entries. A torn final line (crash mid-write) is
+// skipped; corruption anywhere else is an error.
+pub fn read_json_lines<T: DeserializeOwned, P: AsRef<Path>>(path: P, what: &str) -> io::Result<Vec<T>> {
+    let path = path.as_ref();
+    let lines: Vec<String> = BufReader::new(File::open(path)?).lines().collect::<io::Result<_>>()?;
+    let mut entries = Vec::with_capacity(lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<T>(line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) if index + 1 == lines.len() => {
+                eprintln!("[WARN] Ignoring incomplete last {} entry in {}: {}", what, path.display(), e);
+            }
+            Err(e) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: line {}: {}", path.display(), index + 1, e),
+                ))
+            }
+        }
+    }
+    Ok(entries)
+}
+
+pub fn apply_event(portfolio: &mut Portfolio, entry: &JournalEntry) {
+    portfolio.clock = entry.timestamp;
+    match &entry.event {
+        JournalEvent::Opened { .. } | JournalEvent::Checkpoint { .. } | JournalEvent::OrderAccepted { .. } => {}
+        JournalEvent::Fill { stock_id, action, quantity, price, liquidity } => {
+            portfolio.update_with_liquidity(stock_id, *quantity, *price, *action, *liquidity);
+        }
+        JournalEvent::PriceUpdate { stock_id, price } => portfolio.update_last_price(stock_id, *price),
+        JournalEvent::CashMovement { amount, reason } => portfolio.move_cash(*amount, reason),
+        JournalEvent::FinancingCharge { stock_id, amount } => portfolio.charge_financing(stock_id, *amount),
+    }
+}
+
+fn opened_portfolio(entries: &[JournalEntry]) -> io::Result<Portfolio> {
+    match entries.first().map(|entry| &entry.event) {
+        Some(JournalEvent::Opened { initial_balance, cost_basis_method, margin, fee_model }) => {
+            let mut portfolio = Portfolio::with_cost_basis(*initial_balance, *cost_basis_method).with_fee_model(fee_model.clone());
+            portfolio.margin = margin.clone();
+            Ok(portfolio)
+        }
+        Some(JournalEvent::Checkpoint { portfolio }) => Ok((**portfolio).clone()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "journal does not start with an OPENED or CHECKPOINT event",
+        )),
+    }
+}
+
+// Rebuilds a portfolio from the journal, stopping after `up_to_seq` when given.
+pub fn replay_entries(entries: &[JournalEntry], up_to_seq: Option<u64>) -> io::Result<Portfolio> {
+    let mut portfolio = opened_portfolio(entries)?;
+
+    for entry in entries {
+        if up_to_seq.is_some_and(|seq| entry.seq > seq) {
+            break;
+        }
+        apply_event(&mut portfolio, entry);
+    }
+    // Financing was replayed from the journal, so accrual resumes from the last replayed event.
+    portfolio.last_accrual = Some(portfolio.clock);
+    Ok(portfolio)
+}
+
+pub fn replay_journal<P: AsRef<Path>>(path: P) -> io::Result<Portfolio> {
+    replay_entries(&read_journal(path)?, None)
+}
+
+// A replayed journal with each fill as a trade and the equity after every event, for performance reports.
+#[derive(Debug, Clone)]
+pub struct JournalHistory {
+    pub initial_equity: f64,
+    pub portfolio: Portfolio,
+    pub trades: Vec<TradeResponse>,
+    pub equity_curve: Vec<EquityPoint>,
+}
+
+pub fn replay_history(entries: &[JournalEntry], up_to_seq: Option<u64>) -> io::Result<JournalHistory> {
+    let mut portfolio = opened_portfolio(entries)?;
+    let initial_equity = portfolio.equity();
+    let mut trades = Vec::new();
+    let mut equity_curve = Vec::new();
+
+    for entry in entries {
+        if up_to_seq.is_some_and(|seq| entry.seq > seq) {
+            break;
+        }
+        let fees_before = portfolio.total_fees;
+        apply_event(&mut portfolio, entry);
+        match &entry.event {
+            JournalEvent::Opened { .. } | JournalEvent::Checkpoint { .. } | JournalEvent::OrderAccepted { .. } => continue,
+            JournalEvent::Fill { stock_id, action, quantity, price, .. } => trades.push(TradeResponse {
+                stock_id: stock_id.clone(),
+                decision: *action,
+                quantity: *quantity,
+                price: *price,
+                quoted_price: *price,
+                fee: portfolio.total_fees - fees_before,
+                timestamp: entry.timestamp.to_rfc3339(),
+                order_id: None,
+                order_status: None,
+                rejection: None,
+            }),
+            _ => {}
+        }
+        equity_curve.push(EquityPoint {
+            timestamp: entry.timestamp.to_rfc3339(),
+            equity: portfolio.equity(),
+        });
+    }
+    portfolio.last_accrual = Some(portfolio.clock);
+
+    Ok(JournalHistory {
+        initial_equity,
+        portfolio,
+        trades,
+        equity_curve,
+    })
+}
+
+fn compare(differences: &mut Vec<String>, field: &str, replayed: f64, expected: f64) {
+    if (replayed - expected).abs() > EPSILON {
+        differences.push(format!("{}: replayed {:.6}, snapshot {:.6}", field, replayed, expected));
+    }
+}
+
+pub fn diff_portfolios(replayed: &Portfolio, expected: &Portfolio) -> Vec<String> {
+    let mut differences = Vec::new();
+    compare(&mut differences, "balance", replayed.balance, expected.balance);
+    compare(&mut differences, "total_fees", replayed.total_fees, expected.total_fees);
+    compare(&mut differences, "revenue", replayed.revenue, expected.revenue);
+    compare(&mut differences, "total_cost", replayed.total_cost, expected.total_cost);
+    compare(&mut differences, "total_realized_pnl", replayed.total_realized_pnl, expected.total_realized_pnl);
+    compare(&mut differences, "total_financing", replayed.total_financing, expected.total_financing);
+
+    let mut stocks: Vec<&String> = replayed
+        .holdings
+        .keys()
+        .chain(expected.holdings.keys())
+        .chain(replayed.shorts.keys())
+        .chain(expected.shorts.keys())
+        .collect();
+    stocks.sort();
+    stocks.dedup();
+    for stock in stocks {
+        if replayed.short_quantity(stock) != expected.short_quantity(stock) {
+            differences.push(format!(
+                "{} short quantity: replayed {}, snapshot {}",
+                stock,
+                replayed.short_quantity(stock),
+                expected.short_quantity(stock)
+            ));
+        }
+        let (replayed_qty, replayed_avg) = replayed.holdings.get(stock).cloned().unwrap_or((0, 0.0));
+        let (expected_qty, expected_avg) = expected.holdings.get(stock).cloned().unwrap_or((0, 0.0));
+        if replayed_qty != expected_qty {
+            differences.push(format!("{} quantity: replayed {}, snapshot {}", stock, replayed_qty, expected_qty));
+        } else if replayed_qty > 0 {
+            compare(&mut differences, &format!("{} avg cost", stock), replayed_avg, expected_avg);
+        }
+    }
+    differences
+}
+
+// Replays the journal up to the sequence number recorded in the snapshot and reports any mismatch.
+pub fn verify_snapshot<P: AsRef<Path>>(path: P, snapshot: &Snapshot) -> io::Result<Vec<String>> {
+    let entries = read_journal(path)?;
+    let replayed = replay_entries(&entries, snapshot.journal_seq)?;
+    Ok(diff_portfolios(&replayed, &snapshot.portfolio))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::margin::MarginConfig;
+
+    fn journal_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("journal-test-{}-{}.jsonl", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn margin_portfolio() -> Portfolio {
+        Portfolio::with_cost_basis(10000.0, CostBasisMethod::Fifo)
+            .with_margin(MarginConfig::default())
+            .with_fee_model("per-share".parse().unwrap())
+    }
+
+    fn trade(portfolio: &mut Portfolio) {
+        portfolio.update("AAPL", 10, 150.0, TradeAction::Buy);
+        portfolio.update_last_price("AAPL", 155.0);
+        portfolio.update("TSLA", 6, 200.0, TradeAction::Short);
+        portfolio.update("AAPL", 4, 158.0, TradeAction::Sell);
+        portfolio.move_cash(-250.0, "withdrawal");
+        portfolio.update_last_price("TSLA", 190.0);
+        portfolio.update("TSLA", 2, 190.0, TradeAction::Cover);
+    }
+
+    #[test]
+    fn replay_matches_live_portfolio() {
+        let path = journal_path("live");
+        let mut portfolio = margin_portfolio();
+        portfolio.attach_journal(Journal::open(&path, FsyncPolicy::Never).unwrap());
+        trade(&mut portfolio);
+
+        let replayed = replay_journal(&path).unwrap();
+        assert_eq!(diff_portfolios(&replayed, &portfolio), Vec::<String>::new());
+        assert!((replayed.equity() - portfolio.equity()).abs() < EPSILON);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn journal_started_from_a_resumed_portfolio_keeps_its_state() {
+        let path = journal_path("resumed");
+        let mut portfolio = margin_portfolio();
+        trade(&mut portfolio);
+
+        portfolio.attach_journal(Journal::open(&path, FsyncPolicy::Never).unwrap());
+        portfolio.update("MSFT", 5, 300.0, TradeAction::Buy);
+        portfolio.update("AAPL", 6, 160.0, TradeAction::Sell);
+
+        let entries = read_journal(&path).unwrap();
+        assert!(matches!(entries[0].event, JournalEvent::Checkpoint { .. }));
+        let replayed = replay_entries(&entries, None).unwrap();
+        assert_eq!(diff_portfolios(&replayed, &portfolio), Vec::<String>::new());
+        assert_eq!(replayed.short_quantity("TSLA"), 4);
+        assert!((replayed.total_realized_pnl - portfolio.total_realized_pnl).abs() < EPSILON);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn journal_survives_a_crash_mid_write_and_restart() {
+        let path = journal_path("crash");
+        let mut portfolio = margin_portfolio();
+        portfolio.attach_journal(Journal::open(&path, FsyncPolicy::Never).unwrap());
+        trade(&mut portfolio);
+        let last_seq = read_journal(&path).unwrap().last().unwrap().seq;
+        drop(portfolio);
+
+        // The process dies halfway through writing the next entry.
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"seq\":{},\"timest", last_seq + 1).unwrap();
+        drop(file);
+
+        // Restart the way the consumer does: open the journal, replay it, attach, trade on.
+        let journal = Journal::open(&path, FsyncPolicy::Never).unwrap();
+        assert_eq!(journal.last_seq(), last_seq);
+        let mut restarted = replay_journal(&path).unwrap();
+        restarted.attach_journal(journal);
+        restarted.update("MSFT", 5, 300.0, TradeAction::Buy);
+        restarted.update("AAPL", 2, 160.0, TradeAction::Sell);
+
+        let entries = read_journal(&path).unwrap();
+        let seqs: Vec<u64> = entries.iter().map(|entry| entry.seq).collect();
+        assert_eq!(seqs, (1..=seqs.len() as u64).collect::<Vec<_>>());
+        let replayed = replay_entries(&entries, None).unwrap();
+        assert_eq!(diff_portfolios(&replayed, &restarted), Vec::<String>::new());
+        assert_eq!(replayed.holdings["MSFT"].0, 5);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn reopening_completes_an_entry_that_only_lost_its_newline() {
+        let path = journal_path("newline");
+        std::fs::write(&path, "[1]\n[2]").unwrap();
+        let (rows, mut file) = open_json_lines::<Vec<u32>, _>(&path, "test").unwrap();
+        assert_eq!(rows, vec![vec![1], vec![2]]);
+        writeln!(file, "[3]").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "[1]\n[2]\n[3]\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn json_lines_skip_only_a_torn_last_line() {
+        let path = journal_path("torn");
+        std::fs::write(&path, "[1, 2]\n\n[3]\n[4, ").unwrap();
+        let rows: Vec<Vec<u32>> = read_json_lines(&path, "test").unwrap();
+        assert_eq!(rows, vec![vec![1, 2], vec![3]]);
+
+        std::fs::write(&path, "[1, 2]\n[3\n[4]\n").unwrap();
+        let error = read_json_lines::<Vec<u32>, _>(&path, "test").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("line 2"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
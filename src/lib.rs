@@ -1,4 +1,19 @@
 pub mod decision;   
 pub mod portfolio;  
 pub mod models;     
-pub mod producer;   
\ No newline at end of file
+pub mod producer;
+pub mod backtest;
+pub mod metrics;
+pub mod lots;
+pub mod snapshot;
+pub mod journal;
+pub mod margin;
+pub mod orders;
+pub mod exchange;
+pub mod fees;
+pub mod slippage;
+pub mod risk;
+pub mod control;
+pub mod workers;
+pub mod config;
+pub mod blotter;
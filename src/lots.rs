@@ -0,0 +1,220 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+pub const LONG_TERM_DAYS: i64 = 365;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CostBasisMethod {
+    #[default]
+    AverageCost,
+    Fifo,
+    Lifo,
+    HighestCost,
+}
+
+impl FromStr for CostBasisMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace(['-', '_'], "").as_str() {
+            "average" | "averagecost" | "avg" => Ok(CostBasisMethod::AverageCost),
+            "fifo" => Ok(CostBasisMethod::Fifo),
+            "lifo" => Ok(CostBasisMethod::Lifo),
+            "highest" | "highestcost" | "hifo" => Ok(CostBasisMethod::HighestCost),
+            _ => Err(format!("unknown cost basis method '{}': use average, fifo, lifo or highest-cost", s)),
+        }
+    }
+}
+
+impl fmt::Display for CostBasisMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CostBasisMethod::AverageCost => "average-cost",
+            CostBasisMethod::Fifo => "fifo",
+            CostBasisMethod::Lifo => "lifo",
+            CostBasisMethod::HighestCost => "highest-cost",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TaxLot {
+    pub id: u64,
+    pub acquired: DateTime<Utc>,
+    pub quantity: u32,
+    pub price: f64,
+    // Buy-side fee still attributed to the shares left in this lot.
+    pub fee: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoldingTerm {
+    ShortTerm,
+    LongTerm,
+}
+
+impl HoldingTerm {
+    pub fn classify(acquired: DateTime<Utc>, sold: DateTime<Utc>) -> Self {
+        if (sold - acquired).num_days() > LONG_TERM_DAYS {
+            HoldingTerm::LongTerm
+        } else {
+            HoldingTerm::ShortTerm
+        }
+    }
+}
+
+impl fmt::Display for HoldingTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HoldingTerm::ShortTerm => f.write_str("SHORT"),
+            HoldingTerm::LongTerm => f.write_str("LONG"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RealizedGain {
+    pub stock_id: String,
+    pub lot_id: u64,
+    pub quantity: u32,
+    pub acquired: DateTime<Utc>,
+    pub sold: DateTime<Utc>,
+    pub cost_basis: f64,
+    pub proceeds: f64,
+    pub gain: f64,
+    pub term: HoldingTerm,
+}
+
+// Average cost treats every share as identical, so the lots are pooled to one price before a sale.
+fn pool_lots(lots: &mut [TaxLot]) {
+    let quantity: u32 = lots.iter().map(|lot| lot.quantity).sum();
+    if quantity == 0 {
+        return;
+    }
+    let avg_price = lots.iter().map(|lot| lot.price * lot.quantity as f64).sum::<f64>() / quantity as f64;
+    let fee_per_share = lots.iter().map(|lot| lot.fee).sum::<f64>() / quantity as f64;
+    for lot in lots.iter_mut() {
+        lot.price = avg_price;
+        lot.fee = fee_per_share * lot.quantity as f64;
+    }
+}
+
+// Removes `quantity` shares from `lots` in the order the method dictates and returns the slices taken.
+pub fn consume_lots(lots: &mut Vec<TaxLot>, quantity: u32, method: CostBasisMethod) -> Vec<TaxLot> {
+    match method {
+        CostBasisMethod::AverageCost => {
+            pool_lots(lots);
+            lots.sort_by(|a, b| a.acquired.cmp(&b.acquired).then(a.id.cmp(&b.id)));
+        }
+        CostBasisMethod::Fifo => lots.sort_by(|a, b| a.acquired.cmp(&b.acquired).then(a.id.cmp(&b.id))),
+        CostBasisMethod::Lifo => lots.sort_by(|a, b| b.acquired.cmp(&a.acquired).then(b.id.cmp(&a.id))),
+        CostBasisMethod::HighestCost => lots.sort_by(|a, b| b.price.total_cmp(&a.price).then(a.id.cmp(&b.id))),
+    }
+
+    let mut remaining = quantity;
+    let mut consumed = Vec::new();
+    for lot in lots.iter_mut() {
+        if remaining == 0 {
+            break;
+        }
+        let take = remaining.min(lot.quantity);
+        if take == 0 {
+            continue;
+        }
+        let fee = lot.fee * take as f64 / lot.quantity as f64;
+        lot.quantity -= take;
+        lot.fee -= fee;
+        remaining -= take;
+        consumed.push(TaxLot {
+            id: lot.id,
+            acquired: lot.acquired,
+            quantity: take,
+            price: lot.price,
+            fee,
+        });
+    }
+
+    lots.retain(|lot| lot.quantity > 0);
+    lots.sort_by(|a, b| a.acquired.cmp(&b.acquired).then(a.id.cmp(&b.id)));
+    consumed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    // Three lots of 10 bought a day apart at 100, 120 and 90, each carrying a $1 fee.
+    fn lots() -> Vec<TaxLot> {
+        let start = DateTime::parse_from_rfc3339("2024-01-02T14:30:00Z").unwrap().with_timezone(&Utc);
+        [100.0, 120.0, 90.0]
+            .iter()
+            .enumerate()
+            .map(|(index, price)| TaxLot {
+                id: index as u64 + 1,
+                acquired: start + Duration::days(index as i64),
+                quantity: 10,
+                price: *price,
+                fee: 1.0,
+            })
+            .collect()
+    }
+
+    fn taken(lots: &[TaxLot]) -> Vec<(u64, u32)> {
+        lots.iter().map(|lot| (lot.id, lot.quantity)).collect()
+    }
+
+    fn consume(method: CostBasisMethod) -> (Vec<TaxLot>, Vec<TaxLot>) {
+        let mut remaining = lots();
+        let consumed = consume_lots(&mut remaining, 15, method);
+        let fees: f64 = consumed.iter().chain(&remaining).map(|lot| lot.fee).sum();
+        assert!((fees - 3.0).abs() < 1e-9, "{} lost fees: {}", method, fees);
+        (consumed, remaining)
+    }
+
+    #[test]
+    fn fifo_takes_the_oldest_lots_first() {
+        let (consumed, remaining) = consume(CostBasisMethod::Fifo);
+        assert_eq!(taken(&consumed), vec![(1, 10), (2, 5)]);
+        assert_eq!(taken(&remaining), vec![(2, 5), (3, 10)]);
+        assert_eq!(consumed[1].price, 120.0);
+        assert!((consumed[1].fee - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn lifo_takes_the_newest_lots_first() {
+        let (consumed, remaining) = consume(CostBasisMethod::Lifo);
+        assert_eq!(taken(&consumed), vec![(3, 10), (2, 5)]);
+        assert_eq!(taken(&remaining), vec![(1, 10), (2, 5)]);
+    }
+
+    #[test]
+    fn highest_cost_takes_the_dearest_lots_first() {
+        let (consumed, remaining) = consume(CostBasisMethod::HighestCost);
+        assert_eq!(taken(&consumed), vec![(2, 10), (1, 5)]);
+        assert_eq!(taken(&remaining), vec![(1, 5), (3, 10)]);
+        assert_eq!((consumed[0].price, consumed[1].price), (120.0, 100.0));
+    }
+
+    #[test]
+    fn average_cost_pools_prices_and_fees() {
+        let (consumed, remaining) = consume(CostBasisMethod::AverageCost);
+        assert_eq!(taken(&consumed), vec![(1, 10), (2, 5)]);
+        assert_eq!(taken(&remaining), vec![(2, 5), (3, 10)]);
+        for lot in consumed.iter().chain(&remaining) {
+            assert!((lot.price - 310.0 / 3.0).abs() < 1e-9);
+            assert!((lot.fee - 0.1 * lot.quantity as f64).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn consuming_more_than_held_empties_the_lots() {
+        let mut remaining = lots();
+        let consumed = consume_lots(&mut remaining, 50, CostBasisMethod::Fifo);
+        assert_eq!(consumed.iter().map(|lot| lot.quantity).sum::<u32>(), 30);
+        assert!(remaining.is_empty());
+    }
+}
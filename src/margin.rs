@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub const MILLIS_PER_YEAR: f64 = 365.25 * 24.0 * 3600.0 * 1000.0;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MarginConfig {
+    // Fraction of gross exposure that equity must cover when opening positions.
+    pub initial_margin: f64,
+    // Fraction of gross exposure below which a margin call forces liquidation.
+    pub maintenance_margin: f64,
+    // Annual rate charged on the market value of borrowed (shorted) shares.
+    pub borrow_rate: f64,
+    // Annual rate charged on a negative cash balance.
+    pub margin_rate: f64,
+}
+
+impl Default for MarginConfig {
+    fn default() -> Self {
+        MarginConfig {
+            initial_margin: 0.50,
+            maintenance_margin: 0.25,
+            borrow_rate: 0.03,
+            margin_rate: 0.08,
+        }
+    }
+}
+
+impl MarginConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.initial_margin > 0.0 && self.initial_margin <= 1.0) {
+            return Err(format!("initial margin must be in (0, 1], got {}", self.initial_margin));
+        }
+        if !(self.maintenance_margin > 0.0 && self.maintenance_margin <= self.initial_margin) {
+            return Err(format!(
+                "maintenance margin must be in (0, initial margin], got {}",
+                self.maintenance_margin
+            ));
+        }
+        if self.borrow_rate < 0.0 || self.margin_rate < 0.0 {
+            return Err("borrow and margin rates must be non-negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ShortPosition {
+    pub quantity: u32,
+    pub avg_price: f64,
+    pub opened: DateTime<Utc>,
+    // Opening fee still attributed to the shares that remain short.
+    pub fee: f64,
+    pub borrow_fees: f64,
+}
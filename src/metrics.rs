@@ -0,0 +1,300 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::backtest::{BacktestResult, EquityPoint};
+use crate::models::{TradeAction, TradeResponse};
+
+const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 3600.0;
+const DEFAULT_PERIODS_PER_YEAR: f64 = 252.0;
+// Shorter runs aren't annualized: a few minutes scaled to a year gives meaningless (or infinite) figures.
+const MIN_ANNUALIZED_SECS: f64 = 24.0 * 3600.0;
+
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct PerformanceReport {
+    pub initial_equity: f64,
+    pub final_equity: f64,
+    pub total_return: f64,
+    // False when the equity curve spans less than a day. Returns, volatility and the Sharpe and
+    // Sortino ratios are then per period rather than per year, and `annualized_return` is the
+    // return over the whole run.
+    pub annualized: bool,
+    pub annualized_return: f64,
+    pub volatility: f64,
+    pub sharpe_ratio: f64,
+    pub sortino_ratio: f64,
+    pub max_drawdown: f64,
+    pub max_drawdown_duration_periods: usize,
+    pub max_drawdown_duration_secs: i64,
+    pub trade_count: usize,
+    pub closed_trades: usize,
+    pub win_rate: f64,
+    pub profit_factor: f64,
+    pub average_win: f64,
+    pub average_loss: f64,
+    pub turnover: f64,
+    // What fills cost relative to their quoted prices.
+    pub slippage_cost: f64,
+}
+
+fn parse_time(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp).ok().map(|ts| ts.with_timezone(&Utc))
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let avg = mean(values);
+    let variance = values.iter().map(|v| (v - avg).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    variance.sqrt()
+}
+
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator > 0.0 {
+        numerator / denominator
+    } else {
+        0.0
+    }
+}
+
+// Net P&L of every sell or cover against the running average cost of the position it closes.
+fn closed_trade_pnl(trades: &[TradeResponse]) -> Vec<f64> {
+    let mut positions: HashMap<&str, (u32, f64)> = HashMap::new();
+    let mut shorts: HashMap<&str, (u32, f64)> = HashMap::new();
+    let mut pnl = Vec::new();
+
+    for trade in trades.iter().filter(|t| t.quantity > 0) {
+        let quantity = trade.quantity as f64;
+        match trade.decision {
+            TradeAction::Buy => {
+                let entry = positions.entry(trade.stock_id.as_str()).or_insert((0, 0.0));
+                let cost = trade.price * quantity + trade.fee;
+                let total_qty = entry.0 + trade.quantity;
+                entry.1 = (entry.0 as f64 * entry.1 + cost) / total_qty as f64;
+                entry.0 = total_qty;
+            }
+            TradeAction::Sell => {
+                let entry = positions.entry(trade.stock_id.as_str()).or_insert((0, 0.0));
+                let proceeds = trade.price * quantity - trade.fee;
+                pnl.push(proceeds - entry.1 * quantity);
+                entry.0 = entry.0.saturating_sub(trade.quantity);
+            }
+            TradeAction::Short => {
+                let entry = shorts.entry(trade.stock_id.as_str()).or_insert((0, 0.0));
+                let proceeds = trade.price * quantity - trade.fee;
+                let total_qty = entry.0 + trade.quantity;
+                entry.1 = (entry.0 as f64 * entry.1 + proceeds) / total_qty as f64;
+                entry.0 = total_qty;
+            }
+            TradeAction::Cover => {
+                let entry = shorts.entry(trade.stock_id.as_str()).or_insert((0, 0.0));
+                let cost = trade.price * quantity + trade.fee;
+                pnl.push(entry.1 * quantity - cost);
+                entry.0 = entry.0.saturating_sub(trade.quantity);
+            }
+            TradeAction::Refuse => {}
+        }
+    }
+    pnl
+}
+
+impl PerformanceReport {
+    pub fn from_backtest(result: &BacktestResult, initial_equity: f64) -> Self {
+        PerformanceReport::compute(initial_equity, &result.trades, &result.equity_curve)
+    }
+
+    pub fn compute(initial_equity: f64, trades: &[TradeResponse], equity_curve: &[EquityPoint]) -> Self {
+        let mut equities = vec![initial_equity];
+        equities.extend(equity_curve.iter().map(|point| point.equity));
+        let final_equity = *equities.last().unwrap_or(&initial_equity);
+        let total_return = ratio(final_equity, initial_equity) - 1.0;
+
+        let returns: Vec<f64> = equities
+            .windows(2)
+            .filter(|w| w[0] > 0.0)
+            .map(|w| w[1] / w[0] - 1.0)
+            .collect();
+
+        let start = equity_curve.first().and_then(|p| parse_time(&p.timestamp));
+        let end = equity_curve.last().and_then(|p| parse_time(&p.timestamp));
+        // Without timestamps the points are taken to be trading days.
+        let (years, periods_per_year) = match (start, end) {
+            (Some(start), Some(end)) => {
+                let elapsed_secs = (end - start).num_milliseconds() as f64 / 1000.0;
+                let years = elapsed_secs / SECONDS_PER_YEAR;
+                if elapsed_secs >= MIN_ANNUALIZED_SECS {
+                    (Some(years), Some(returns.len() as f64 / years))
+                } else {
+                    (None, None)
+                }
+            }
+            _ => (None, Some(DEFAULT_PERIODS_PER_YEAR)),
+        };
+        let annualized = periods_per_year.is_some();
+
+        let annualized_return = match years {
+            Some(years) if final_equity > 0.0 && initial_equity > 0.0 => (final_equity / initial_equity).powf(1.0 / years) - 1.0,
+            _ => total_return,
+        };
+
+        let avg_return = mean(&returns);
+        let return_std = std_dev(&returns);
+        let downside: Vec<f64> = returns.iter().map(|r| r.min(0.0)).collect();
+        let downside_dev = (downside.iter().map(|r| r * r).sum::<f64>() / downside.len().max(1) as f64).sqrt();
+        let scale = periods_per_year.map_or(1.0, f64::sqrt);
+
+        let mut peak = initial_equity;
+        let mut peak_index = 0;
+        let mut max_drawdown: f64 = 0.0;
+        let mut max_drawdown_duration_periods = 0;
+        let mut max_drawdown_duration_secs = 0;
+        for (index, equity) in equities.iter().enumerate() {
+            if *equity >= peak {
+                peak = *equity;
+                peak_index = index;
+            } else if peak > 0.0 {
+                max_drawdown = max_drawdown.max((peak - equity) / peak);
+            }
+            let duration = index - peak_index;
+            if duration > max_drawdown_duration_periods {
+                max_drawdown_duration_periods = duration;
+                // equities[0] is the initial equity, so curve indices are shifted by one.
+                let from = equity_curve.get(peak_index.saturating_sub(1)).and_then(|p| parse_time(&p.timestamp));
+                let to = equity_curve.get(index - 1).and_then(|p| parse_time(&p.timestamp));
+                if let (Some(from), Some(to)) = (from, to) {
+                    max_drawdown_duration_secs = (to - from).num_seconds();
+                }
+            }
+        }
+
+        let pnl = closed_trade_pnl(trades);
+        let wins: Vec<f64> = pnl.iter().cloned().filter(|p| *p > 0.0).collect();
+        let losses: Vec<f64> = pnl.iter().cloned().filter(|p| *p < 0.0).collect();
+        let gross_profit: f64 = wins.iter().sum();
+        let gross_loss: f64 = -losses.iter().sum::<f64>();
+
+        let traded_notional: f64 = trades.iter().map(|t| t.price * t.quantity as f64).sum();
+        let slippage_cost: f64 = trades
+            .iter()
+            .filter(|t| t.quoted_price > 0.0)
+            .map(|t| {
+                let adverse = match t.decision {
+                    TradeAction::Buy | TradeAction::Cover => t.price - t.quoted_price,
+                    _ => t.quoted_price - t.price,
+                };
+                adverse * t.quantity as f64
+            })
+            .sum();
+
+        PerformanceReport {
+            initial_equity,
+            final_equity,
+            total_return,
+            annualized,
+            annualized_return,
+            volatility: return_std * scale,
+            sharpe_ratio: ratio(avg_return, return_std) * scale,
+            sortino_ratio: ratio(avg_return, downside_dev) * scale,
+            max_drawdown,
+            max_drawdown_duration_periods,
+            max_drawdown_duration_secs,
+            trade_count: trades.len(),
+            closed_trades: pnl.len(),
+            win_rate: ratio(wins.len() as f64, pnl.len() as f64),
+            profit_factor: ratio(gross_profit, gross_loss),
+            average_win: mean(&wins),
+            average_loss: mean(&losses),
+            turnover: ratio(traded_notional, mean(&equities)),
+            slippage_cost,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_json()?)
+    }
+
+    pub fn display(&self) {
+        println!("\n--- Performance Report ---\n");
+        println!("Initial Equity:      ${:.2}", self.initial_equity);
+        println!("Final Equity:        ${:.2}", self.final_equity);
+        println!("Total Return:        {:.2}%", self.total_return * 100.0);
+        if self.annualized {
+            println!("Annualized Return:   {:.2}%", self.annualized_return * 100.0);
+            println!("Volatility (ann.):   {:.2}%", self.volatility * 100.0);
+            println!("Sharpe Ratio:        {:.3}", self.sharpe_ratio);
+            println!("Sortino Ratio:       {:.3}", self.sortino_ratio);
+        } else {
+            // Under a day of data: nothing is scaled to a year.
+            println!("Annualized Return:   N/A (run shorter than a day)");
+            println!("Volatility (period): {:.4}%", self.volatility * 100.0);
+            println!("Sharpe (per period): {:.3}", self.sharpe_ratio);
+            println!("Sortino (per period): {:.3}", self.sortino_ratio);
+        }
+        println!("Max Drawdown:        {:.2}%", self.max_drawdown * 100.0);
+        println!(
+            "Max DD Duration:     {} periods ({}s)",
+            self.max_drawdown_duration_periods, self.max_drawdown_duration_secs
+        );
+        println!("Trades Executed:     {}", self.trade_count);
+        println!("Closed Trades:       {}", self.closed_trades);
+        println!("Win Rate:            {:.2}%", self.win_rate * 100.0);
+        println!("Profit Factor:       {:.3}", self.profit_factor);
+        println!("Average Win:         ${:.2}", self.average_win);
+        println!("Average Loss:        ${:.2}", self.average_loss);
+        println!("Turnover:            {:.2}x", self.turnover);
+        println!("Slippage Cost:       ${:.2}", self.slippage_cost);
+        println!("-------------------------------------------------------------\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn curve(step: Duration, equities: &[f64]) -> Vec<EquityPoint> {
+        let start = DateTime::parse_from_rfc3339("2024-01-02T14:30:00Z").unwrap().with_timezone(&Utc);
+        equities
+            .iter()
+            .enumerate()
+            .map(|(index, equity)| EquityPoint {
+                timestamp: (start + step * index as i32).to_rfc3339(),
+                equity: *equity,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn short_runs_are_not_annualized() {
+        let equities = [10000.0, 10020.0, 10010.0, 10040.0, 10055.0];
+        let report = PerformanceReport::compute(10000.0, &[], &curve(Duration::seconds(45), &equities));
+        assert!(!report.annualized);
+        assert!((report.annualized_return - 0.0055).abs() < 1e-9);
+        assert!(report.sharpe_ratio.is_finite() && report.sharpe_ratio < 10.0);
+        assert!(!report.to_json().unwrap().contains("null"));
+    }
+
+    #[test]
+    fn runs_of_a_day_or_more_are_annualized() {
+        let equities: Vec<f64> = (0..30).map(|day| 10000.0 * (1.0 + 0.001 * day as f64)).collect();
+        let report = PerformanceReport::compute(10000.0, &[], &curve(Duration::days(1), &equities));
+        assert!(report.annualized);
+        assert!(report.annualized_return > report.total_return);
+        assert!(report.annualized_return.is_finite());
+    }
+}
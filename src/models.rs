@@ -1,10 +1,55 @@
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use crate::orders::OrderStatus;
+use crate::risk::RiskRejection;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TradeAction {
+    Buy,
+    Sell,
+    Short,
+    Cover,
+    Refuse,
+}
+
+impl TradeAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TradeAction::Buy => "BUY",
+            TradeAction::Sell => "SELL",
+            TradeAction::Short => "SHORT",
+            TradeAction::Cover => "COVER",
+            TradeAction::Refuse => "REFUSE",
+        }
+    }
+}
+
+impl fmt::Display for TradeAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MessageType {
+    PriceUpdate,
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageType::PriceUpdate => f.write_str("PRICE_UPDATE"),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TradeMessage {
     pub stock_id: String,
     pub current_price: f64,
-    pub action_type: String, 
+    pub action_type: MessageType,
     pub quantity: u32,
     pub timestamp: String,
 }
@@ -12,8 +57,20 @@ pub struct TradeMessage {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TradeResponse {
     pub stock_id: String,
-    pub decision: String, 
+    pub decision: TradeAction,
     pub quantity: u32,
+    // Average price actually paid or received.
     pub price: f64,
+    // Price quoted by the update that triggered the fill; differs from `price` by slippage.
+    #[serde(default)]
+    pub quoted_price: f64,
+    #[serde(default)]
+    pub fee: f64,
     pub timestamp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_status: Option<OrderStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejection: Option<RiskRejection>,
 }
@@ -0,0 +1,820 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use crate::decision::execute_trade_action;
+use crate::exchange::MatchingEngine;
+use crate::fees::Liquidity;
+use crate::models::{MessageType, TradeAction, TradeMessage, TradeResponse};
+use crate::portfolio::Portfolio;
+use crate::risk::{RiskEngine, RiskRejection};
+use crate::slippage::{SlippageModel, VolatilityEstimator};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderType {
+    Market,
+    Limit { limit: f64 },
+    Stop { stop: f64 },
+    StopLimit { stop: f64, limit: f64 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TimeInForce {
+    Gtc,
+    Day,
+    Ioc,
+    Fok,
+}
+
+impl FromStr for TimeInForce {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_uppercase().as_str() {
+            "GTC" => Ok(TimeInForce::Gtc),
+            "DAY" => Ok(TimeInForce::Day),
+            "IOC" => Ok(TimeInForce::Ioc),
+            "FOK" => Ok(TimeInForce::Fok),
+            _ => Err(format!("unknown time in force '{}': use GTC, DAY, IOC or FOK", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Expired,
+    // Refused by pre-trade risk checks before reaching the market.
+    Rejected,
+}
+
+impl OrderStatus {
+    pub fn is_open(&self) -> bool {
+        matches!(self, OrderStatus::New | OrderStatus::PartiallyFilled)
+    }
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OrderStatus::New => "NEW",
+            OrderStatus::PartiallyFilled => "PARTIALLY_FILLED",
+            OrderStatus::Filled => "FILLED",
+            OrderStatus::Cancelled => "CANCELLED",
+            OrderStatus::Expired => "EXPIRED",
+            OrderStatus::Rejected => "REJECTED",
+        };
+        f.write_str(name)
+    }
+}
+
+// What a strategy asks for; the order manager turns it into a tracked `Order`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderRequest {
+    pub stock_id: String,
+    pub action: TradeAction,
+    pub quantity: u32,
+    pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
+}
+
+impl OrderRequest {
+    pub fn market(stock_id: &str, action: TradeAction, quantity: u32) -> Self {
+        OrderRequest {
+            stock_id: stock_id.to_string(),
+            action,
+            quantity,
+            order_type: OrderType::Market,
+            time_in_force: TimeInForce::Ioc,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Order {
+    pub id: u64,
+    pub stock_id: String,
+    pub action: TradeAction,
+    pub quantity: u32,
+    pub filled_quantity: u32,
+    pub avg_fill_price: f64,
+    pub order_type: OrderType,
+    pub time_in_force: TimeInForce,
+    pub status: OrderStatus,
+    pub created: DateTime<Utc>,
+    pub triggered: bool,
+}
+
+impl Order {
+    pub fn remaining(&self) -> u32 {
+        self.quantity - self.filled_quantity
+    }
+
+    fn limit_price(&self) -> Option<f64> {
+        match self.order_type {
+            OrderType::Limit { limit } | OrderType::StopLimit { limit, .. } => Some(limit),
+            OrderType::Market | OrderType::Stop { .. } => None,
+        }
+    }
+
+    // Only GTC and DAY orders outlive the update they were submitted on.
+    fn rests(&self) -> bool {
+        matches!(self.time_in_force, TimeInForce::Gtc | TimeInForce::Day) && self.order_type != OrderType::Market
+    }
+
+    fn is_buy_side(&self) -> bool {
+        matches!(self.action, TradeAction::Buy | TradeAction::Cover)
+    }
+
+    // Stop orders arm once the price trades through the stop; everything else is armed from the start.
+    fn update_trigger(&mut self, price: f64) {
+        if self.triggered {
+            return;
+        }
+        let stop = match self.order_type {
+            OrderType::Stop { stop } | OrderType::StopLimit { stop, .. } => stop,
+            OrderType::Market | OrderType::Limit { .. } => {
+                self.triggered = true;
+                return;
+            }
+        };
+        self.triggered = if self.is_buy_side() { price >= stop } else { price <= stop };
+    }
+
+    fn is_marketable(&self, price: f64) -> bool {
+        if !self.triggered {
+            return false;
+        }
+        match self.order_type {
+            OrderType::Market | OrderType::Stop { .. } => true,
+            OrderType::Limit { limit } | OrderType::StopLimit { limit, .. } => {
+                if self.is_buy_side() {
+                    price <= limit
+                } else {
+                    price >= limit
+                }
+            }
+        }
+    }
+
+    fn record_fill(&mut self, quantity: u32, price: f64) {
+        let filled_value = self.avg_fill_price * self.filled_quantity as f64 + price * quantity as f64;
+        self.filled_quantity += quantity;
+        self.avg_fill_price = filled_value / self.filled_quantity as f64;
+        self.status = if self.remaining() == 0 {
+            OrderStatus::Filled
+        } else {
+            OrderStatus::PartiallyFilled
+        };
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OrderManager {
+    next_id: u64,
+    pub pending: HashMap<String, Vec<Order>>,
+    pub completed: Vec<Order>,
+    // When attached, orders match against the simulated book instead of filling at the quoted price.
+    exchange: Option<MatchingEngine>,
+    // Applied to fills at the quoted price; book fills already pay for walking the book.
+    slippage: SlippageModel,
+    volatility: VolatilityEstimator,
+    risk: Option<RiskEngine>,
+    // Kill-switch state set from the control channel.
+    halted_all: bool,
+    halted_symbols: HashSet<String>,
+}
+
+impl OrderManager {
+    pub fn new() -> Self {
+        OrderManager {
+            next_id: 1,
+            pending: HashMap::new(),
+            completed: Vec::new(),
+            exchange: None,
+            slippage: SlippageModel::None,
+            volatility: VolatilityEstimator::default(),
+            risk: None,
+            halted_all: false,
+            halted_symbols: HashSet::new(),
+        }
+    }
+
+    pub fn with_risk(mut self, risk: RiskEngine) -> Self {
+        self.risk = Some(risk);
+        self
+    }
+
+    pub fn risk(&self) -> Option<&RiskEngine> {
+        self.risk.as_ref()
+    }
+
+    pub fn with_slippage(mut self, slippage: SlippageModel) -> Self {
+        self.slippage = slippage;
+        self
+    }
+
+    pub fn slippage(&self) -> SlippageModel {
+        self.slippage
+    }
+
+    pub fn volatility(&self, stock_id: &str) -> f64 {
+        self.volatility.volatility(stock_id)
+    }
+
+    fn quote_context(&self, message: &TradeMessage) -> QuoteContext {
+        QuoteContext {
+            price: message.current_price,
+            available: message.quantity,
+            volatility: self.volatility.volatility(&message.stock_id),
+            slippage: self.slippage,
+        }
+    }
+
+    pub fn with_exchange(mut self, engine: MatchingEngine) -> Self {
+        self.exchange = Some(engine);
+        self
+    }
+
+    pub fn exchange(&self) -> Option<&MatchingEngine> {
+        self.exchange.as_ref()
+    }
+
+    // The price update as the strategy should see it: with an exchange attached, the quoted
+    // quantity is the depth actually displayed in the book rather than the producer's figure.
+    pub fn quote(&self, message: &TradeMessage) -> TradeMessage {
+        let mut quote = message.clone();
+        if let Some(engine) = &self.exchange {
+            quote.quantity = engine.displayed_depth(&message.stock_id);
+        }
+        quote
+    }
+
+    pub fn is_halted(&self, stock_id: &str) -> bool {
+        self.halted_all || self.halted_symbols.contains(stock_id)
+    }
+
+    pub fn halted_symbols(&self) -> Vec<&str> {
+        let mut symbols: Vec<&str> = self.halted_symbols.iter().map(|symbol| symbol.as_str()).collect();
+        symbols.sort();
+        symbols
+    }
+
+    // Stops new orders everywhere and pulls every resting one.
+    pub fn halt_all(&mut self) {
+        self.halted_all = true;
+        let open: Vec<u64> = self.pending.values().flatten().map(|order| order.id).collect();
+        for order_id in open {
+            self.cancel(order_id);
+        }
+        println!("[Order Manager] All trading halted.");
+    }
+
+    pub fn halt_symbol(&mut self, stock_id: &str) {
+        self.halted_symbols.insert(stock_id.to_string());
+        let open: Vec<u64> = self.open_orders(stock_id).iter().map(|order| order.id).collect();
+        for order_id in open {
+            self.cancel(order_id);
+        }
+        println!("[Order Manager] Trading in {} halted.", stock_id);
+    }
+
+    // Without a symbol every halt is lifted; with one, only that symbol's halt.
+    pub fn resume(&mut self, stock_id: Option<&str>) {
+        match stock_id {
+            Some(stock_id) => {
+                self.halted_symbols.remove(stock_id);
+                println!("[Order Manager] Trading in {} resumed.", stock_id);
+            }
+            None => {
+                self.halted_all = false;
+                self.halted_symbols.clear();
+                println!("[Order Manager] All trading resumed.");
+            }
+        }
+    }
+
+    // Kill switch: halts everything, then closes every long and short at its last price with
+    // market orders that skip the halt and risk checks.
+    pub fn flatten_all(&mut self, portfolio: &mut Portfolio, timestamp: &str) -> Vec<TradeResponse> {
+        self.halt_all();
+        let mut positions: Vec<(String, TradeAction, u32)> = portfolio
+            .holdings
+            .iter()
+            .filter(|(_, (quantity, _))| *quantity > 0)
+            .map(|(stock_id, (quantity, _))| (stock_id.clone(), TradeAction::Sell, *quantity))
+            .chain(
+                portfolio
+                    .shorts
+                    .iter()
+                    .map(|(stock_id, position)| (stock_id.clone(), TradeAction::Cover, position.quantity)),
+            )
+            .collect();
+        positions.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut responses = Vec::new();
+        for (stock_id, action, quantity) in positions {
+            let Some(price) = portfolio.last_prices.get(&stock_id).cloned() else {
+                println!("[Order Manager] Cannot flatten {}: no price seen yet.", stock_id);
+                continue;
+            };
+            let message = TradeMessage {
+                stock_id: stock_id.clone(),
+                current_price: price,
+                action_type: MessageType::PriceUpdate,
+                quantity,
+                timestamp: timestamp.to_string(),
+            };
+            let request = OrderRequest::market(&stock_id, action, quantity);
+            responses.push(self.route(portfolio, request, &message, timestamp, false));
+        }
+        println!("[Order Manager] Flattened {} positions.", responses.len());
+        responses
+    }
+
+    pub fn open_orders(&self, stock_id: &str) -> &[Order] {
+        self.pending.get(stock_id).map(|orders| orders.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn get(&self, order_id: u64) -> Option<&Order> {
+        self.pending
+            .values()
+            .flatten()
+            .chain(self.completed.iter())
+            .find(|order| order.id == order_id)
+    }
+
+    // Pulls every resting order without halting anything, e.g. before a forced liquidation, and
+    // reports each one as CANCELLED at its symbol's last price.
+    pub fn cancel_all(&mut self, portfolio: &Portfolio, timestamp: &str) -> Vec<TradeResponse> {
+        let open: Vec<u64> = self.pending.values().flatten().map(|order| order.id).collect();
+        open.into_iter()
+            .filter_map(|order_id| self.cancel(order_id))
+            .map(|order| {
+                let price = portfolio.last_prices.get(&order.stock_id).cloned().unwrap_or(0.0);
+                order_response(&order, &[], price, timestamp)
+            })
+            .collect()
+    }
+
+    pub fn cancel(&mut self, order_id: u64) -> Option<Order> {
+        for orders in self.pending.values_mut() {
+            if let Some(index) = orders.iter().position(|order| order.id == order_id) {
+                let mut order = orders.remove(index);
+                order.status = OrderStatus::Cancelled;
+                if let Some(engine) = self.exchange.as_mut() {
+                    engine.cancel(&order.stock_id, order.id);
+                }
+                println!("[Order Manager] Cancelled order #{} ({} {} of {}).", order.id, order.action, order.remaining(), order.stock_id);
+                self.completed.push(order.clone());
+                return Some(order);
+            }
+        }
+        None
+    }
+
+    // Accepts a new order and tries to fill it against the current quote before it can rest.
+    pub fn submit(
+        &mut self,
+        portfolio: &mut Portfolio,
+        request: OrderRequest,
+        message: &TradeMessage,
+        timestamp: &str,
+    ) -> TradeResponse {
+        self.route(portfolio, request, message, timestamp, true)
+    }
+
+    fn route(
+        &mut self,
+        portfolio: &mut Portfolio,
+        request: OrderRequest,
+        message: &TradeMessage,
+        timestamp: &str,
+        checks: bool,
+    ) -> TradeResponse {
+        let mut order = Order {
+            id: self.next_id,
+            stock_id: request.stock_id,
+            action: request.action,
+            quantity: request.quantity,
+            filled_quantity: 0,
+            avg_fill_price: 0.0,
+            order_type: request.order_type,
+            time_in_force: request.time_in_force,
+            status: OrderStatus::New,
+            created: portfolio.clock,
+            triggered: false,
+        };
+        self.next_id += 1;
+
+        if checks {
+            let halted = if self.halted_all {
+                Some(RiskRejection::Halted { stock_id: None })
+            } else if self.halted_symbols.contains(&order.stock_id) {
+                Some(RiskRejection::Halted { stock_id: Some(order.stock_id.clone()) })
+            } else {
+                None
+            };
+            let price = order.limit_price().unwrap_or(message.current_price);
+            let verdict = match (halted, self.risk.as_mut()) {
+                (Some(rejection), _) => Err(rejection),
+                (None, Some(risk)) => risk.check(portfolio, &order.stock_id, order.action, order.quantity, price),
+                (None, None) => Ok(()),
+            };
+            if let Err(rejection) = verdict {
+                println!(
+                    "[Risk] Order #{} rejected ({} {} of {}): {}.",
+                    order.id, order.action, order.quantity, order.stock_id, rejection
+                );
+                order.status = OrderStatus::Rejected;
+                let mut response = order_response(&order, &[], message.current_price, timestamp);
+                response.rejection = Some(rejection);
+                self.completed.push(order);
+                return response;
+            }
+        }
+
+        // Market orders take the full quote as before; resting-style orders only get the quoted quantity.
+        let liquidity = match order.order_type {
+            OrderType::Market => order.quantity,
+            _ => message.quantity,
+        };
+        let quote = self.quote_context(message);
+        let fills = work_order(&mut self.exchange, portfolio, &mut order, &quote, liquidity, Liquidity::Taker);
+
+        if order.status.is_open() {
+            if order.rests() {
+                let queue = self
+                    .exchange
+                    .as_ref()
+                    .and_then(|engine| engine.queue_position(&order.stock_id, order.id))
+                    .map(|ahead| format!(", {} shares ahead in queue", ahead))
+                    .unwrap_or_default();
+                println!(
+                    "[Order Manager] Order #{} resting: {} {} of {} ({:?}, {:?}{}).",
+                    order.id, order.action, order.remaining(), order.stock_id, order.order_type, order.time_in_force, queue
+                );
+            } else {
+                order.status = OrderStatus::Cancelled;
+            }
+        }
+
+        let response = order_response(&order, &fills, message.current_price, timestamp);
+        if order.status.is_open() {
+            self.pending.entry(order.stock_id.clone()).or_default().push(order);
+        } else {
+            self.completed.push(order);
+        }
+        response
+    }
+
+    // Re-evaluates resting orders for the symbol in time priority; returns one response per fill or expiry.
+    pub fn on_price_update(
+        &mut self,
+        portfolio: &mut Portfolio,
+        message: &TradeMessage,
+        timestamp: &str,
+    ) -> Vec<TradeResponse> {
+        self.volatility.update(&message.stock_id, message.current_price);
+        let book_fills = match self.exchange.as_mut() {
+            Some(engine) => engine.on_price_update(message),
+            None => Vec::new(),
+        };
+        let Some(mut orders) = self.pending.remove(&message.stock_id) else {
+            return Vec::new();
+        };
+
+        let now = portfolio.clock;
+        let quote = self.quote_context(message);
+        let mut liquidity = message.quantity;
+        let mut responses = Vec::new();
+        for order in orders.iter_mut() {
+            if order.time_in_force == TimeInForce::Day && now.date_naive() > order.created.date_naive() {
+                order.status = OrderStatus::Expired;
+                if let Some(engine) = self.exchange.as_mut() {
+                    engine.cancel(&order.stock_id, order.id);
+                }
+                println!("[Order Manager] Order #{} for {} expired at end of day.", order.id, order.stock_id);
+                responses.push(order_response(order, &[], message.current_price, timestamp));
+                continue;
+            }
+
+            let resting: Vec<(f64, u32, Liquidity)> = book_fills
+                .iter()
+                .filter(|fill| fill.order_id == order.id)
+                .map(|fill| (fill.price, fill.quantity, Liquidity::Maker))
+                .collect();
+            let mut fills = apply_fills(&mut self.exchange, portfolio, order, resting);
+
+            let in_book = self
+                .exchange
+                .as_ref()
+                .is_some_and(|engine| engine.queue_position(&order.stock_id, order.id).is_some());
+            if order.status.is_open() && !in_book && liquidity > 0 {
+                // Without an exchange, a limit order filling after it rested was hit rather than crossing.
+                let quote_liquidity = match order.limit_price() {
+                    Some(_) => Liquidity::Maker,
+                    None => Liquidity::Taker,
+                };
+                let taken = work_order(&mut self.exchange, portfolio, order, &quote, liquidity, quote_liquidity);
+                if self.exchange.is_none() {
+                    liquidity -= taken.iter().map(|execution| execution.quantity).sum::<u32>();
+                }
+                fills.extend(taken);
+            }
+            if !fills.is_empty() {
+                responses.push(order_response(order, &fills, message.current_price, timestamp));
+            }
+        }
+
+        let (open, done): (Vec<Order>, Vec<Order>) = orders.into_iter().partition(|order| order.status.is_open());
+        self.completed.extend(done);
+        if !open.is_empty() {
+            self.pending.insert(message.stock_id.clone(), open);
+        }
+        responses
+    }
+}
+
+impl Default for OrderManager {
+    fn default() -> Self {
+        OrderManager::new()
+    }
+}
+
+// A fill the portfolio accepted, with the fee it was charged.
+#[derive(Debug, Clone, Copy)]
+struct Execution {
+    price: f64,
+    quantity: u32,
+    fee: f64,
+}
+
+// The current update as seen by a fill at the quoted price.
+#[derive(Debug, Clone, Copy)]
+struct QuoteContext {
+    price: f64,
+    available: u32,
+    volatility: f64,
+    slippage: SlippageModel,
+}
+
+// Tries to fill the order now: at the quoted price (less slippage) up to `liquidity` shares, or
+// against the book when an exchange is attached. Quote fills are booked as `quote_liquidity`.
+fn work_order(
+    exchange: &mut Option<MatchingEngine>,
+    portfolio: &mut Portfolio,
+    order: &mut Order,
+    quote: &QuoteContext,
+    liquidity: u32,
+    quote_liquidity: Liquidity,
+) -> Vec<Execution> {
+    let price = quote.price;
+    order.update_trigger(price);
+    let fills = match exchange.as_mut() {
+        Some(engine) => {
+            if !order.triggered {
+                if order.time_in_force == TimeInForce::Fok {
+                    order.status = OrderStatus::Cancelled;
+                }
+                return Vec::new();
+            }
+            let limit = order.limit_price();
+            if order.time_in_force == TimeInForce::Fok {
+                let available = engine.available(&order.stock_id, order.action, limit);
+                if available < order.remaining() {
+                    println!(
+                        "[Order Manager] FOK order #{} cancelled: only {} of {} {} available in the book.",
+                        order.id, available, order.remaining(), order.stock_id
+                    );
+                    order.status = OrderStatus::Cancelled;
+                    return Vec::new();
+                }
+            }
+            let rest = order.rests() && limit.is_some();
+            engine
+                .submit(order.id, &order.stock_id, order.action, order.remaining(), limit, rest)
+                .into_iter()
+                .map(|fill| (fill.price, fill.quantity, Liquidity::Taker))
+                .collect()
+        }
+        None => {
+            if !order.is_marketable(price) {
+                if order.time_in_force == TimeInForce::Fok {
+                    order.status = OrderStatus::Cancelled;
+                }
+                return Vec::new();
+            }
+            let quantity = order.remaining().min(liquidity);
+            if order.time_in_force == TimeInForce::Fok && quantity < order.remaining() {
+                println!(
+                    "[Order Manager] FOK order #{} cancelled: only {} of {} {} available.",
+                    order.id, liquidity, order.remaining(), order.stock_id
+                );
+                order.status = OrderStatus::Cancelled;
+                return Vec::new();
+            }
+            if quantity == 0 {
+                return Vec::new();
+            }
+            let slipped = quote.slippage.fill_price(order.action, price, quantity, quote.available, quote.volatility);
+            // Slippage never pushes a limit order through its limit.
+            let fill_price = match order.limit_price() {
+                Some(limit) if order.is_buy_side() => slipped.min(limit),
+                Some(limit) => slipped.max(limit),
+                None => slipped,
+            };
+            vec![(fill_price, quantity, quote_liquidity)]
+        }
+    };
+    apply_fills(exchange, portfolio, order, fills)
+}
+
+// Books each fill in the portfolio; a fill the portfolio refuses cancels the rest of the order.
+fn apply_fills(
+    exchange: &mut Option<MatchingEngine>,
+    portfolio: &mut Portfolio,
+    order: &mut Order,
+    fills: Vec<(f64, u32, Liquidity)>,
+) -> Vec<Execution> {
+    let mut accepted = Vec::new();
+    for (price, quantity, liquidity) in fills {
+        let fees_before = portfolio.total_fees;
+        match execute_trade_action(portfolio, &order.stock_id, price, order.action, Some(quantity), liquidity) {
+            Some(executed) if executed > 0 => {
+                order.record_fill(executed, price);
+                accepted.push(Execution {
+                    price,
+                    quantity: executed,
+                    fee: portfolio.total_fees - fees_before,
+                });
+            }
+            _ => {
+                println!("[Order Manager] Order #{} rejected by the portfolio and cancelled.", order.id);
+                order.status = OrderStatus::Cancelled;
+                if let Some(engine) = exchange.as_mut() {
+                    engine.cancel(&order.stock_id, order.id);
+                }
+                break;
+            }
+        }
+    }
+    accepted
+}
+
+// One response per order event; the price is the average of this event's fills, or the quote when nothing filled.
+fn order_response(order: &Order, fills: &[Execution], quoted_price: f64, timestamp: &str) -> TradeResponse {
+    let quantity: u32 = fills.iter().map(|execution| execution.quantity).sum();
+    let price = if let [execution] = fills {
+        execution.price
+    } else if quantity > 0 {
+        fills.iter().map(|execution| execution.price * execution.quantity as f64).sum::<f64>() / quantity as f64
+    } else {
+        quoted_price
+    };
+    TradeResponse {
+        stock_id: order.stock_id.clone(),
+        decision: order.action,
+        quantity,
+        price,
+        quoted_price,
+        fee: fills.iter().map(|execution| execution.fee).sum(),
+        timestamp: timestamp.to_string(),
+        order_id: Some(order.id),
+        order_status: Some(order.status),
+        rejection: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY_ONE: &str = "2024-01-02T15:00:00Z";
+    const DAY_TWO: &str = "2024-01-03T15:00:00Z";
+
+    // A quote offering `available` shares of AAPL.
+    fn quote(price: f64, available: u32, timestamp: &str) -> TradeMessage {
+        TradeMessage {
+            stock_id: "AAPL".to_string(),
+            current_price: price,
+            action_type: MessageType::PriceUpdate,
+            quantity: available,
+            timestamp: timestamp.to_string(),
+        }
+    }
+
+    fn request(action: TradeAction, quantity: u32, order_type: OrderType, time_in_force: TimeInForce) -> OrderRequest {
+        OrderRequest {
+            order_type,
+            time_in_force,
+            ..OrderRequest::market("AAPL", action, quantity)
+        }
+    }
+
+    fn setup() -> (Portfolio, OrderManager) {
+        let mut portfolio = Portfolio::new(100000.0);
+        portfolio.advance_clock(DAY_ONE);
+        (portfolio, OrderManager::new())
+    }
+
+    fn held(portfolio: &Portfolio) -> u32 {
+        portfolio.holdings.get("AAPL").map(|(quantity, _)| *quantity).unwrap_or(0)
+    }
+
+    #[test]
+    fn limit_order_rests_until_the_price_reaches_it() {
+        let (mut portfolio, mut orders) = setup();
+        let limit = request(TradeAction::Buy, 10, OrderType::Limit { limit: 95.0 }, TimeInForce::Gtc);
+        let response = orders.submit(&mut portfolio, limit, &quote(100.0, 50, DAY_ONE), DAY_ONE);
+        assert_eq!((response.quantity, response.order_status), (0, Some(OrderStatus::New)));
+        assert_eq!(orders.open_orders("AAPL").len(), 1);
+
+        assert!(orders.on_price_update(&mut portfolio, &quote(96.0, 50, DAY_ONE), DAY_ONE).is_empty());
+
+        let responses = orders.on_price_update(&mut portfolio, &quote(94.0, 50, DAY_ONE), DAY_ONE);
+        assert_eq!(responses.len(), 1);
+        assert_eq!((responses[0].quantity, responses[0].price), (10, 94.0));
+        assert_eq!(responses[0].order_status, Some(OrderStatus::Filled));
+        assert!(orders.open_orders("AAPL").is_empty());
+        assert_eq!(held(&portfolio), 10);
+    }
+
+    #[test]
+    fn resting_order_fills_across_updates_as_liquidity_arrives() {
+        let (mut portfolio, mut orders) = setup();
+        let limit = request(TradeAction::Buy, 25, OrderType::Limit { limit: 100.0 }, TimeInForce::Gtc);
+        let response = orders.submit(&mut portfolio, limit, &quote(100.0, 10, DAY_ONE), DAY_ONE);
+        assert_eq!((response.quantity, response.order_status), (10, Some(OrderStatus::PartiallyFilled)));
+
+        let responses = orders.on_price_update(&mut portfolio, &quote(99.0, 10, DAY_ONE), DAY_ONE);
+        assert_eq!((responses[0].quantity, responses[0].order_status), (10, Some(OrderStatus::PartiallyFilled)));
+        let responses = orders.on_price_update(&mut portfolio, &quote(98.0, 10, DAY_ONE), DAY_ONE);
+        assert_eq!((responses[0].quantity, responses[0].order_status), (5, Some(OrderStatus::Filled)));
+        assert_eq!(held(&portfolio), 25);
+    }
+
+    #[test]
+    fn ioc_cancels_whatever_does_not_fill_at_once() {
+        let (mut portfolio, mut orders) = setup();
+        let ioc = request(TradeAction::Buy, 25, OrderType::Limit { limit: 100.0 }, TimeInForce::Ioc);
+        let response = orders.submit(&mut portfolio, ioc, &quote(100.0, 10, DAY_ONE), DAY_ONE);
+        assert_eq!((response.quantity, response.order_status), (10, Some(OrderStatus::Cancelled)));
+        assert!(orders.open_orders("AAPL").is_empty());
+        assert_eq!(held(&portfolio), 10);
+    }
+
+    #[test]
+    fn fok_fills_completely_or_not_at_all() {
+        let (mut portfolio, mut orders) = setup();
+        let fok = request(TradeAction::Buy, 25, OrderType::Limit { limit: 100.0 }, TimeInForce::Fok);
+        let response = orders.submit(&mut portfolio, fok, &quote(100.0, 10, DAY_ONE), DAY_ONE);
+        assert_eq!((response.quantity, response.order_status), (0, Some(OrderStatus::Cancelled)));
+        assert_eq!(held(&portfolio), 0);
+
+        let fok = request(TradeAction::Buy, 25, OrderType::Limit { limit: 100.0 }, TimeInForce::Fok);
+        let response = orders.submit(&mut portfolio, fok, &quote(100.0, 30, DAY_ONE), DAY_ONE);
+        assert_eq!((response.quantity, response.order_status), (25, Some(OrderStatus::Filled)));
+        assert_eq!(held(&portfolio), 25);
+    }
+
+    #[test]
+    fn day_orders_expire_overnight_and_gtc_orders_do_not() {
+        let (mut portfolio, mut orders) = setup();
+        let day = request(TradeAction::Buy, 10, OrderType::Limit { limit: 90.0 }, TimeInForce::Day);
+        let gtc = request(TradeAction::Buy, 10, OrderType::Limit { limit: 90.0 }, TimeInForce::Gtc);
+        let day_id = orders.submit(&mut portfolio, day, &quote(100.0, 50, DAY_ONE), DAY_ONE).order_id;
+        let gtc_id = orders.submit(&mut portfolio, gtc, &quote(100.0, 50, DAY_ONE), DAY_ONE).order_id;
+
+        assert!(orders.on_price_update(&mut portfolio, &quote(99.0, 50, DAY_ONE), DAY_ONE).is_empty());
+        assert_eq!(orders.open_orders("AAPL").len(), 2);
+
+        portfolio.advance_clock(DAY_TWO);
+        let responses = orders.on_price_update(&mut portfolio, &quote(99.0, 50, DAY_TWO), DAY_TWO);
+        assert_eq!(responses.len(), 1);
+        assert_eq!((responses[0].order_id, responses[0].order_status), (day_id, Some(OrderStatus::Expired)));
+        let open: Vec<Option<u64>> = orders.open_orders("AAPL").iter().map(|order| Some(order.id)).collect();
+        assert_eq!(open, vec![gtc_id]);
+    }
+
+    #[test]
+    fn stop_order_waits_for_the_price_to_trade_through() {
+        let (mut portfolio, mut orders) = setup();
+        orders.submit(&mut portfolio, OrderRequest::market("AAPL", TradeAction::Buy, 10), &quote(100.0, 50, DAY_ONE), DAY_ONE);
+        let stop = request(TradeAction::Sell, 10, OrderType::Stop { stop: 95.0 }, TimeInForce::Gtc);
+        let response = orders.submit(&mut portfolio, stop, &quote(100.0, 50, DAY_ONE), DAY_ONE);
+        assert_eq!((response.quantity, response.order_status), (0, Some(OrderStatus::New)));
+
+        assert!(orders.on_price_update(&mut portfolio, &quote(96.0, 50, DAY_ONE), DAY_ONE).is_empty());
+        let responses = orders.on_price_update(&mut portfolio, &quote(94.0, 50, DAY_ONE), DAY_ONE);
+        assert_eq!((responses[0].quantity, responses[0].price), (10, 94.0));
+        assert_eq!(held(&portfolio), 0);
+    }
+}
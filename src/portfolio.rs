@@ -1,58 +1,177 @@
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use crate::fees::{FeeModel, Liquidity};
+use crate::journal::{Journal, JournalEvent};
+use crate::lots::{consume_lots, CostBasisMethod, HoldingTerm, RealizedGain, TaxLot};
+use crate::margin::{MarginConfig, ShortPosition, MILLIS_PER_YEAR};
+use crate::models::TradeAction;
 
-pub const FEE_RATE: f64 = 0.001;
-
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Portfolio {
     pub holdings: HashMap<String, (u32, f64)>,
+    pub initial_balance: f64,
     pub balance: f64,
     pub cash_flow: f64,
     pub total_fees: f64,
     pub revenue: f64,
     pub total_cost: f64,
     pub last_prices: HashMap<String, f64>,
+    pub realized_pnl: HashMap<String, f64>,
+    pub total_realized_pnl: f64,
+    pub lots: HashMap<String, Vec<TaxLot>>,
+    pub realized_gains: Vec<RealizedGain>,
+    pub cost_basis_method: CostBasisMethod,
+    pub next_lot_id: u64,
+    pub clock: DateTime<Utc>,
+    pub margin: Option<MarginConfig>,
+    pub shorts: HashMap<String, ShortPosition>,
+    pub total_financing: f64,
+    pub last_accrual: Option<DateTime<Utc>>,
+    pub fee_model: FeeModel,
+    // Notional traded in `volume_month` (as yyyymm), for volume-tiered fee schedules.
+    pub monthly_volume: f64,
+    pub volume_month: u32,
+    #[serde(skip)]
+    pub journal: Option<Arc<Mutex<Journal>>>,
 }
 
 impl Portfolio {
     pub fn new(initial_balance: f64) -> Self {
+        Portfolio::with_cost_basis(initial_balance, CostBasisMethod::default())
+    }
+
+    pub fn with_cost_basis(initial_balance: f64, cost_basis_method: CostBasisMethod) -> Self {
         Portfolio {
             holdings: HashMap::new(),
+            initial_balance,
             balance: initial_balance,
             cash_flow: 0.0,
             total_fees: 0.0,
             revenue: 0.0,
             total_cost: 0.0,
             last_prices: HashMap::new(),
+            realized_pnl: HashMap::new(),
+            total_realized_pnl: 0.0,
+            lots: HashMap::new(),
+            realized_gains: Vec::new(),
+            cost_basis_method,
+            next_lot_id: 1,
+            clock: Utc::now(),
+            margin: None,
+            shorts: HashMap::new(),
+            total_financing: 0.0,
+            last_accrual: None,
+            fee_model: FeeModel::default(),
+            monthly_volume: 0.0,
+            volume_month: 0,
+            journal: None,
         }
     }
 
-    pub fn update(&mut self, stock_id: &str, quantity: u32, price: f64, action: &str) {
+    pub fn with_margin(mut self, margin: MarginConfig) -> Self {
+        self.margin = Some(margin);
+        self
+    }
+
+    pub fn with_fee_model(mut self, fee_model: FeeModel) -> Self {
+        self.fee_model = fee_model;
+        self
+    }
+
+    fn current_month(&self) -> u32 {
+        self.clock.year() as u32 * 100 + self.clock.month()
+    }
+
+    // Notional traded so far in the calendar month of the portfolio clock.
+    pub fn monthly_volume(&self) -> f64 {
+        if self.volume_month == self.current_month() {
+            self.monthly_volume
+        } else {
+            0.0
+        }
+    }
+
+    fn add_volume(&mut self, notional: f64) {
+        let month = self.current_month();
+        if self.volume_month != month {
+            self.volume_month = month;
+            self.monthly_volume = 0.0;
+        }
+        self.monthly_volume += notional;
+    }
+
+    pub fn fee_for(&self, action: TradeAction, quantity: u32, price: f64, liquidity: Liquidity) -> f64 {
+        self.fee_model.fee(action, quantity, price, liquidity, self.monthly_volume())
+    }
+
+    // Most shares that `budget` covers at `price`, fees included.
+    pub fn affordable_quantity(&self, action: TradeAction, budget: f64, price: f64) -> u32 {
+        self.fee_model.affordable_quantity(action, budget, price, self.monthly_volume())
+    }
+
+    pub fn update(&mut self, stock_id: &str, quantity: u32, price: f64, action: TradeAction) -> bool {
+        self.update_with_liquidity(stock_id, quantity, price, action, Liquidity::Taker)
+    }
+
+    pub fn update_with_liquidity(
+        &mut self,
+        stock_id: &str,
+        quantity: u32,
+        price: f64,
+        action: TradeAction,
+        liquidity: Liquidity,
+    ) -> bool {
         if quantity == 0 || price < 0.0 {
             println!(
                 "[INFO] Transaction not processed: Quantity {} must be greater than zero, and price ${:.2} must be non-negative. Action: {}.",
                 quantity, price, action
             );
-            return;
+            return false;
         }
 
-        if self.balance < 0.0 {
+        if self.margin.is_none() && self.balance < 0.0 {
             println!(
                 "[INFO] Action paused: Portfolio balance is -${:.2}. Please review your financial standing before performing further actions.",
                 self.balance
             );
-            return;
+            return false;
         }
 
-        let fee = FEE_RATE * price * quantity as f64; 
-        match action {
-            "BUY" => {
-                let cost = price * quantity as f64 + fee;
-                if self.balance >= cost {
-                    let entry = self.holdings.entry(stock_id.to_string()).or_insert((0, 0.0));
-                    let new_total_qty = entry.0 + quantity;
-                    let new_avg_cost = ((entry.0 as f64 * entry.1) + (quantity as f64 * price)) / new_total_qty as f64;
+        let opens_exposure = matches!(action, TradeAction::Buy | TradeAction::Short);
+        if opens_exposure && self.margin_deficit().is_some() {
+            println!(
+                "[INFO] Action paused: {} of {} refused while the account is under a margin call.",
+                action, stock_id
+            );
+            return false;
+        }
 
-                    entry.0 = new_total_qty;
-                    entry.1 = new_avg_cost;
+        let fee = self.fee_for(action, quantity, price, liquidity);
+        let filled = match action {
+            TradeAction::Buy => {
+                let cost = price * quantity as f64 + fee;
+                if self.short_quantity(stock_id) > 0 {
+                    println!(
+                        "[Consumer] Cannot buy {} while {} shares are short - COVER the short position first.",
+                        stock_id, self.short_quantity(stock_id)
+                    );
+                    false
+                } else if self.buying_power() >= cost {
+                    if !self.record(JournalEvent::Fill { stock_id: stock_id.to_string(), action, quantity, price, liquidity }) {
+                        return false;
+                    }
+                    let lot = TaxLot {
+                        id: self.next_lot_id,
+                        acquired: self.clock,
+                        quantity,
+                        price,
+                        fee,
+                    };
+                    self.next_lot_id += 1;
+                    self.lots.entry(stock_id.to_string()).or_default().push(lot);
+                    self.refresh_holding(stock_id);
                     self.balance -= cost;
                     self.total_cost += cost;
                     self.total_fees += fee;
@@ -61,39 +180,362 @@ impl Portfolio {
                         "[Consumer] Bought {} shares of {} at ${:.2} - Cost: ${:.2} (incl. ${:.2} fee).",
                         quantity, stock_id, price, cost, fee
                     );
+                    true
                 } else {
                     println!(
                         "[Consumer] Insufficient funds to buy {} shares of {} - Need: ${:.2}, Have: ${:.2}.",
-                        quantity, stock_id, cost, self.balance
+                        quantity, stock_id, cost, self.buying_power()
                     );
+                    false
                 }
             }
-            "SELL" => {
-                let entry = self.holdings.entry(stock_id.to_string()).or_insert((0, 0.0));
-                if entry.0 >= quantity {
+            TradeAction::Sell => {
+                let owned = self.get_stock_quantity(stock_id);
+                if owned >= quantity {
+                    if !self.record(JournalEvent::Fill { stock_id: stock_id.to_string(), action, quantity, price, liquidity }) {
+                        return false;
+                    }
                     let revenue = price * quantity as f64 - fee;
-                    entry.0 -= quantity;
+                    let lots = self.lots.entry(stock_id.to_string()).or_default();
+                    let consumed = consume_lots(lots, quantity, self.cost_basis_method);
+
+                    let mut realized = 0.0;
+                    for lot in consumed {
+                        let proceeds = revenue * lot.quantity as f64 / quantity as f64;
+                        let cost_basis = lot.price * lot.quantity as f64 + lot.fee;
+                        let gain = proceeds - cost_basis;
+                        realized += gain;
+                        self.realized_gains.push(RealizedGain {
+                            stock_id: stock_id.to_string(),
+                            lot_id: lot.id,
+                            quantity: lot.quantity,
+                            acquired: lot.acquired,
+                            sold: self.clock,
+                            cost_basis,
+                            proceeds,
+                            gain,
+                            term: HoldingTerm::classify(lot.acquired, self.clock),
+                        });
+                    }
+
+                    self.refresh_holding(stock_id);
                     self.balance += revenue;
                     self.revenue += revenue;
                     self.total_fees += fee;
                     self.cash_flow += revenue;
+                    *self.realized_pnl.entry(stock_id.to_string()).or_insert(0.0) += realized;
+                    self.total_realized_pnl += realized;
                     println!(
-                        "[Consumer] Sold {} shares of {} at ${:.2} - Revenue: ${:.2} (incl. ${:.2} fee).",
-                        quantity, stock_id, price, revenue, fee
+                        "[Consumer] Sold {} shares of {} at ${:.2} - Revenue: ${:.2} (incl. ${:.2} fee), Realized P/L: ${:.2}.",
+                        quantity, stock_id, price, revenue, fee, realized
                     );
+                    true
                 } else {
                     println!(
                         "[Consumer] Not enough shares to sell {} of {} - Owned: {}.",
-                        quantity, stock_id, entry.0
+                        quantity, stock_id, owned
                     );
+                    false
                 }
             }
-            _ => {
-                println!("[ERROR] Skip Unknown action '{}'.", action);
+            TradeAction::Short => {
+                let notional = price * quantity as f64;
+                if self.margin.is_none() {
+                    println!("[Consumer] Short sale of {} refused: portfolio has no margin account.", stock_id);
+                    false
+                } else if self.get_stock_quantity(stock_id) > 0 {
+                    println!(
+                        "[Consumer] Cannot short {} while holding {} shares - SELL the long position first.",
+                        stock_id, self.get_stock_quantity(stock_id)
+                    );
+                    false
+                } else if self.buying_power() < notional + fee {
+                    println!(
+                        "[Consumer] Insufficient margin to short {} shares of {} - Need: ${:.2}, Buying Power: ${:.2}.",
+                        quantity, stock_id, notional + fee, self.buying_power()
+                    );
+                    false
+                } else {
+                    if !self.record(JournalEvent::Fill { stock_id: stock_id.to_string(), action, quantity, price, liquidity }) {
+                        return false;
+                    }
+                    let proceeds = notional - fee;
+                    let clock = self.clock;
+                    let position = self.shorts.entry(stock_id.to_string()).or_insert(ShortPosition {
+                        quantity: 0,
+                        avg_price: 0.0,
+                        opened: clock,
+                        fee: 0.0,
+                        borrow_fees: 0.0,
+                    });
+                    let total_qty = position.quantity + quantity;
+                    position.avg_price = (position.avg_price * position.quantity as f64 + notional) / total_qty as f64;
+                    position.quantity = total_qty;
+                    position.fee += fee;
+                    self.balance += proceeds;
+                    self.revenue += proceeds;
+                    self.total_fees += fee;
+                    self.cash_flow += proceeds;
+                    println!(
+                        "[Consumer] Shorted {} shares of {} at ${:.2} - Proceeds: ${:.2} (incl. ${:.2} fee).",
+                        quantity, stock_id, price, proceeds, fee
+                    );
+                    true
+                }
+            }
+            TradeAction::Cover => {
+                let short_qty = self.short_quantity(stock_id);
+                if short_qty < quantity {
+                    println!(
+                        "[Consumer] Not enough short shares to cover {} of {} - Short: {}.",
+                        quantity, stock_id, short_qty
+                    );
+                    return false;
+                }
+                if !self.record(JournalEvent::Fill { stock_id: stock_id.to_string(), action, quantity, price, liquidity }) {
+                    return false;
+                }
+                let cost = price * quantity as f64 + fee;
+                let Some(position) = self.shorts.get_mut(stock_id) else {
+                    return false;
+                };
+                let opening_fee = position.fee * quantity as f64 / position.quantity as f64;
+                position.fee -= opening_fee;
+                position.quantity -= quantity;
+                let proceeds = position.avg_price * quantity as f64 - opening_fee;
+                let gain = proceeds - cost;
+                let opened = position.opened;
+                if position.quantity == 0 {
+                    self.shorts.remove(stock_id);
+                }
+
+                self.realized_gains.push(RealizedGain {
+                    stock_id: stock_id.to_string(),
+                    lot_id: 0,
+                    quantity,
+                    acquired: opened,
+                    sold: self.clock,
+                    cost_basis: cost,
+                    proceeds,
+                    gain,
+                    term: HoldingTerm::ShortTerm,
+                });
+                self.balance -= cost;
+                self.total_cost += cost;
+                self.total_fees += fee;
+                self.cash_flow -= cost;
+                *self.realized_pnl.entry(stock_id.to_string()).or_insert(0.0) += gain;
+                self.total_realized_pnl += gain;
+                println!(
+                    "[Consumer] Covered {} shares of {} at ${:.2} - Cost: ${:.2} (incl. ${:.2} fee), Realized P/L: ${:.2}.",
+                    quantity, stock_id, price, cost, fee, gain
+                );
+                true
+            }
+            TradeAction::Refuse => {
+                println!("[INFO] Skip REFUSE action for {}: nothing to update.", stock_id);
+                false
             }
+        };
+        if filled {
+            self.add_volume(price * quantity as f64);
+        }
+        filled
+    }
+
+    pub fn short_quantity(&self, stock_id: &str) -> u32 {
+        self.shorts.get(stock_id).map(|position| position.quantity).unwrap_or(0)
+    }
+
+    // Long shares minus short shares.
+    pub fn net_position(&self, stock_id: &str) -> i64 {
+        self.get_stock_quantity(stock_id) as i64 - self.short_quantity(stock_id) as i64
+    }
+
+    pub fn short_market_value(&self) -> f64 {
+        self.shorts
+            .iter()
+            .map(|(stock, position)| {
+                let price = self.last_prices.get(stock).cloned().unwrap_or(position.avg_price);
+                price * position.quantity as f64
+            })
+            .sum()
+    }
+
+    pub fn gross_exposure(&self) -> f64 {
+        self.market_value() + self.short_market_value()
+    }
+
+    // Notional that can still be opened: cash for a cash account, equity leverage for a margin account.
+    pub fn buying_power(&self) -> f64 {
+        match &self.margin {
+            None => self.balance,
+            Some(margin) => (self.equity() / margin.initial_margin - self.gross_exposure()).max(0.0),
+        }
+    }
+
+    // Amount of equity missing to meet maintenance margin, if the account is under a margin call.
+    pub fn margin_deficit(&self) -> Option<f64> {
+        let margin = self.margin.as_ref()?;
+        let required = margin.maintenance_margin * self.gross_exposure();
+        let equity = self.equity();
+        if equity < required {
+            Some(required - equity)
+        } else {
+            None
         }
     }
 
+    // The order that closes the largest open position, used for forced liquidation.
+    pub fn liquidation_order(&self) -> Option<(String, TradeAction, u32, f64)> {
+        let longs = self.holdings.iter().filter(|(_, (qty, _))| *qty > 0).map(|(stock, (qty, avg_cost))| {
+            let price = self.last_prices.get(stock).cloned().unwrap_or(*avg_cost);
+            (stock.clone(), TradeAction::Sell, *qty, price)
+        });
+        let shorts = self.shorts.iter().map(|(stock, position)| {
+            let price = self.last_prices.get(stock).cloned().unwrap_or(position.avg_price);
+            (stock.clone(), TradeAction::Cover, position.quantity, price)
+        });
+        longs
+            .chain(shorts)
+            .max_by(|a, b| (a.2 as f64 * a.3).total_cmp(&(b.2 as f64 * b.3)))
+    }
+
+    // Charges borrow fees on shorts and interest on a negative balance for the time since the last accrual.
+    pub fn accrue_financing(&mut self, now: DateTime<Utc>) {
+        let Some(last) = self.last_accrual else {
+            self.last_accrual = Some(now);
+            return;
+        };
+        if now <= last {
+            return;
+        }
+        self.last_accrual = Some(now);
+        let Some(margin) = self.margin.clone() else {
+            return;
+        };
+
+        let years = (now - last).num_milliseconds() as f64 / MILLIS_PER_YEAR;
+        let stocks: Vec<String> = self.shorts.keys().cloned().collect();
+        for stock in stocks {
+            let value = self.short_quantity(&stock) as f64
+                * self.last_prices.get(&stock).cloned().unwrap_or(self.shorts[&stock].avg_price);
+            let fee = value * margin.borrow_rate * years;
+            if fee > 0.0 {
+                self.charge_financing(&stock, fee);
+            }
+        }
+
+        let interest = (-self.balance).max(0.0) * margin.margin_rate * years;
+        if interest > 0.0 {
+            self.charge_financing("", interest);
+        }
+    }
+
+    // A borrow fee when `stock_id` names a short position, margin interest when it is empty.
+    pub fn charge_financing(&mut self, stock_id: &str, amount: f64) {
+        if !self.record(JournalEvent::FinancingCharge { stock_id: stock_id.to_string(), amount }) {
+            return;
+        }
+        if let Some(position) = self.shorts.get_mut(stock_id) {
+            position.borrow_fees += amount;
+        }
+        self.balance -= amount;
+        self.cash_flow -= amount;
+        self.total_financing += amount;
+    }
+
+    // Starts write-ahead journaling. An empty journal is seeded with the opening state: just the
+    // settings for a fresh portfolio, the whole portfolio for one that already traded (e.g. resumed
+    // from a snapshot), so replaying the journal never loses its positions or realized P&L.
+    pub fn attach_journal(&mut self, journal: Journal) {
+        let opening = journal.is_empty().then(|| {
+            if self.has_history() {
+                JournalEvent::Checkpoint { portfolio: Box::new(self.clone()) }
+            } else {
+                JournalEvent::Opened {
+                    initial_balance: self.balance,
+                    cost_basis_method: self.cost_basis_method,
+                    margin: self.margin.clone(),
+                    fee_model: self.fee_model.clone(),
+                }
+            }
+        });
+        self.journal = Some(Arc::new(Mutex::new(journal)));
+        if let Some(event) = opening {
+            self.record(event);
+        }
+    }
+
+    fn has_history(&self) -> bool {
+        self.balance != self.initial_balance
+            || self.cash_flow != 0.0
+            || self.total_fees != 0.0
+            || self.total_financing != 0.0
+            || self.holdings.values().any(|(quantity, _)| *quantity > 0)
+            || !self.shorts.is_empty()
+            || !self.realized_gains.is_empty()
+            || !self.last_prices.is_empty()
+    }
+
+    pub fn journal_seq(&self) -> Option<u64> {
+        self.journal.as_ref().map(|journal| journal.lock().unwrap().last_seq())
+    }
+
+    // Appends the event before the caller mutates state; returns false if the write failed.
+    pub fn record(&mut self, event: JournalEvent) -> bool {
+        let Some(journal) = &self.journal else {
+            return true;
+        };
+        let mut journal = journal.lock().unwrap();
+        match journal.append(self.clock, &event) {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("[ERROR] Failed to write journal {}: {}. Change not applied.", journal.path().display(), e);
+                false
+            }
+        }
+    }
+
+    pub fn move_cash(&mut self, amount: f64, reason: &str) {
+        if self.margin.is_none() && self.balance + amount < 0.0 {
+            println!(
+                "[INFO] Cash movement of ${:.2} ({}) refused: balance is only ${:.2}.",
+                amount, reason, self.balance
+            );
+            return;
+        }
+        if !self.record(JournalEvent::CashMovement { amount, reason: reason.to_string() }) {
+            return;
+        }
+        self.balance += amount;
+        self.cash_flow += amount;
+        println!("[Consumer] Cash movement of ${:.2} ({}). Balance: ${:.2}.", amount, reason, self.balance);
+    }
+
+    // Rebuilds the aggregate (quantity, average price) view of a symbol from its open lots.
+    fn refresh_holding(&mut self, stock_id: &str) {
+        let lots = self.lots.get(stock_id).map(|lots| lots.as_slice()).unwrap_or(&[]);
+        let quantity: u32 = lots.iter().map(|lot| lot.quantity).sum();
+        let entry = self.holdings.entry(stock_id.to_string()).or_insert((0, 0.0));
+        entry.0 = quantity;
+        if quantity > 0 {
+            entry.1 = lots.iter().map(|lot| lot.price * lot.quantity as f64).sum::<f64>() / quantity as f64;
+        }
+    }
+
+    // Moves the portfolio's event-time clock to the timestamp of the message being processed.
+    pub fn advance_clock(&mut self, timestamp: &str) {
+        if let Ok(ts) = DateTime::parse_from_rfc3339(timestamp) {
+            self.clock = ts.with_timezone(&Utc);
+            self.accrue_financing(self.clock);
+        }
+    }
+
+    pub fn realized_by_term(&self, term: HoldingTerm) -> f64 {
+        self.realized_gains.iter().filter(|g| g.term == term).map(|g| g.gain).sum()
+    }
+
     pub fn update_last_price(&mut self, stock_id: &str, price: f64) {
         if price < 0.0 {
             println!(
@@ -102,6 +544,9 @@ impl Portfolio {
             );
             return;
         }
+        if !self.record(JournalEvent::PriceUpdate { stock_id: stock_id.to_string(), price }) {
+            return;
+        }
         self.last_prices.insert(stock_id.to_string(), price);
     }
 
@@ -109,31 +554,165 @@ impl Portfolio {
         self.holdings.get(stock_id).map(|(qty, _)| *qty).unwrap_or(0)
     }
 
+    pub fn market_value(&self) -> f64 {
+        self.holdings
+            .iter()
+            .map(|(stock, (quantity, avg_cost))| {
+                let price = self.last_prices.get(stock).cloned().unwrap_or(*avg_cost);
+                price * *quantity as f64
+            })
+            .sum()
+    }
+
+    // Open long and short P&L of one symbol at its last price. The fees paid to open the position
+    // are charged here, as they are to realized P&L on close, so realized plus unrealized P&L
+    // always adds up to the change in equity.
+    pub fn unrealized_pnl_for(&self, stock_id: &str) -> f64 {
+        let long = match self.holdings.get(stock_id) {
+            Some((quantity, avg_cost)) if *quantity > 0 => {
+                let price = self.last_prices.get(stock_id).cloned().unwrap_or(*avg_cost);
+                let fees: f64 = self.lots.get(stock_id).map(|lots| lots.iter().map(|lot| lot.fee).sum()).unwrap_or(0.0);
+                (price - avg_cost) * *quantity as f64 - fees
+            }
+            _ => 0.0,
+        };
+        let short = match self.shorts.get(stock_id) {
+            Some(position) => {
+                let price = self.last_prices.get(stock_id).cloned().unwrap_or(position.avg_price);
+                (position.avg_price - price) * position.quantity as f64 - position.fee
+            }
+            None => 0.0,
+        };
+        long + short
+    }
+
+    pub fn unrealized_pnl(&self) -> f64 {
+        let mut stocks: Vec<&String> = self.holdings.keys().chain(self.shorts.keys()).collect();
+        stocks.sort();
+        stocks.dedup();
+        stocks.into_iter().map(|stock| self.unrealized_pnl_for(stock)).sum()
+    }
+
+    // Everything earned or lost by trading: equals the change in equity less any cash movements.
+    pub fn net_pnl(&self) -> f64 {
+        self.total_realized_pnl + self.unrealized_pnl() - self.total_financing
+    }
+
+    pub fn equity(&self) -> f64 {
+        self.balance + self.market_value() - self.short_market_value()
+    }
+
     pub fn display_summary(&self) {
         if self.balance < 0.0 {
             println!("[Info] Portfolio has negative balance.");
         }
 
-        let initial_cash = self.balance + self.total_cost - self.revenue;
-        println!("\n--- Portfolio Summary ---\n");
-        println!("Initial Cash:        ${:.2}", initial_cash);
+        println!("\n--- Portfolio Summary ({} cost basis) ---\n", self.cost_basis_method);
+        println!("Initial Cash:        ${:.2}", self.initial_balance);
         println!("Final Cash:          ${:.2}", self.balance);
         println!("Total Revenue:       ${:.2}", self.revenue);
         println!("Total Cost:          ${:.2}", self.total_cost);
-        println!("Net Profit/Loss:     ${:.2}", self.revenue - self.total_cost);
         println!("Total Fees Paid:     ${:.2}", self.total_fees);
+        println!("Financing Charges:   ${:.2}", self.total_financing);
         println!("Net Cash Flow:       ${:.2}", self.cash_flow);
+        println!("Realized P/L:        ${:.2}", self.total_realized_pnl);
+        println!("  Short-Term:        ${:.2}", self.realized_by_term(HoldingTerm::ShortTerm));
+        println!("  Long-Term:         ${:.2}", self.realized_by_term(HoldingTerm::LongTerm));
+        println!("Unrealized P/L:      ${:.2}", self.unrealized_pnl());
+        println!("Net Profit/Loss:     ${:.2}", self.net_pnl());
+        if let Some(margin) = &self.margin {
+            println!("Equity:              ${:.2}", self.equity());
+            println!("Gross Exposure:      ${:.2}", self.gross_exposure());
+            println!("Buying Power:        ${:.2}", self.buying_power());
+            println!(
+                "Margin (IM / MM):    {:.0}% / {:.0}%",
+                margin.initial_margin * 100.0,
+                margin.maintenance_margin * 100.0
+            );
+            match self.margin_deficit() {
+                Some(deficit) => println!("Margin Status:       MARGIN CALL (deficit ${:.2})", deficit),
+                None => println!("Margin Status:       OK"),
+            }
+        }
         println!("\nHoldings:");
-        println!("Stock  | Shares   | Avg Cost    | Current Price | Unrealized P/L");
-        println!("-------------------------------------------------------------");
-        for (stock, (quantity, avg_cost)) in &self.holdings {
-            let current_price = self.last_prices.get(stock).cloned().unwrap_or(*avg_cost);
-            let unrealized_pl = (current_price - avg_cost) * (*quantity as f64);
+        println!("Stock  | Shares   | Avg Cost    | Current Price | Unrealized P/L | Realized P/L");
+        println!("------------------------------------------------------------------------------");
+        let mut stocks: Vec<&String> = self
+            .holdings
+            .keys()
+            .chain(self.realized_pnl.keys())
+            .chain(self.shorts.keys())
+            .collect();
+        stocks.sort();
+        stocks.dedup();
+        for stock in stocks {
+            let realized_pl = self.realized_pnl.get(stock).cloned().unwrap_or(0.0);
+            let (quantity, avg_cost, unrealized_pl, current_price) = match self.shorts.get(stock) {
+                Some(position) => {
+                    let current_price = self.last_prices.get(stock).cloned().unwrap_or(position.avg_price);
+                    (-(position.quantity as i64), position.avg_price, self.unrealized_pnl_for(stock), current_price)
+                }
+                None => {
+                    let (quantity, avg_cost) = self.holdings.get(stock).cloned().unwrap_or((0, 0.0));
+                    let current_price = self.last_prices.get(stock).cloned().unwrap_or(avg_cost);
+                    (quantity as i64, avg_cost, self.unrealized_pnl_for(stock), current_price)
+                }
+            };
+            println!(
+                "{:<6} | {:<8} | ${:<10.2} | ${:<12.2} | ${:<13.2} | ${:<10.2}",
+                stock, quantity, avg_cost, current_price, unrealized_pl, realized_pl
+            );
+        }
+        println!("------------------------------------------------------------------------------\n");
+    }
+
+    pub fn display_realized_gains(&self) {
+        println!("\nRealized Gains by Lot:");
+        println!("Stock  | Lot    | Shares   | Acquired             | Sold                 | Cost Basis  | Proceeds    | Gain        | Term");
+        println!("--------------------------------------------------------------------------------------------------------------------");
+        for gain in &self.realized_gains {
             println!(
-                "{:<6} | {:<8} | ${:<10.2} | ${:<12.2} | ${:<10.2}",
-                stock, quantity, avg_cost, current_price, unrealized_pl
+                "{:<6} | {:<6} | {:<8} | {:<20} | {:<20} | ${:<10.2} | ${:<10.2} | ${:<10.2} | {}",
+                gain.stock_id,
+                gain.lot_id,
+                gain.quantity,
+                gain.acquired.format("%Y-%m-%d %H:%M:%S"),
+                gain.sold.format("%Y-%m-%d %H:%M:%S"),
+                gain.cost_basis,
+                gain.proceeds,
+                gain.gain,
+                gain.term
             );
         }
-        println!("-------------------------------------------------------------\n");
+        println!("--------------------------------------------------------------------------------------------------------------------\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f64 = 1e-9;
+
+    #[test]
+    fn net_pnl_reconciles_with_equity_change() {
+        let mut portfolio = Portfolio::new(10000.0)
+            .with_margin(MarginConfig::default())
+            .with_fee_model("per-share".parse().unwrap());
+        let initial_equity = portfolio.equity();
+
+        portfolio.update("AAPL", 10, 150.0, TradeAction::Buy);
+        portfolio.update("AAPL", 6, 152.0, TradeAction::Buy);
+        portfolio.update_last_price("AAPL", 149.0);
+        assert!((portfolio.net_pnl() - (portfolio.equity() - initial_equity)).abs() < EPSILON);
+
+        portfolio.update("AAPL", 7, 155.0, TradeAction::Sell);
+        portfolio.update("TSLA", 5, 200.0, TradeAction::Short);
+        portfolio.update("TSLA", 2, 195.0, TradeAction::Cover);
+        portfolio.update_last_price("TSLA", 205.0);
+        portfolio.charge_financing("TSLA", 1.25);
+
+        assert!(portfolio.total_fees > 0.0);
+        assert!((portfolio.net_pnl() - (portfolio.equity() - initial_equity)).abs() < EPSILON);
     }
 }
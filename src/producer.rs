@@ -1,59 +1,444 @@
-use chrono::Utc;
-use rand::Rng;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Poisson, StandardNormal};
+use serde::Deserialize;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::mpsc;
 use std::thread;
 use std::time::{Duration, Instant};
-use crate::models::TradeMessage;
+use crate::models::{MessageType, TradeMessage};
+
+pub const STOCKS: [&str; 59] = [
+    "AAPL", "GOOGL", "AMZN", "META", "MSFT", "TSLA", "NFLX", "NVDA", 
+    "BABA", "ORCL", "INTC", "CSCO", "ADBE", "IBM", "PYPL",
+    "V", "AMD", "QCOM", "INTU", "CRM", "UBER", "LYFT", "SNAP", 
+    "TWTR", "SQ", "ZM", "SHOP", "ASML", "TXN", "ADSK",
+    "JPM", "BAC", "C", "GS", "AXP", "VZ", "SCHW",
+    "WMT", "DIS", "MCD", "NKE", "LOW", "HD", "SBUX", "TGT", "ROKU",
+    "PFE", "MRK", "JNJ", "UNH", "CVS", "GILD", "AMGN", "BMY", "SNY",
+    "XOM", "CVX", "BA", "GE"
+];
+
+pub const UNKNOWN_SECTOR: &str = "other";
+
+pub const DEFAULT_TICK_INTERVAL: Duration = Duration::from_millis(300);
+
+// Sector of a simulated symbol; anything else (e.g. replayed data) is `UNKNOWN_SECTOR`.
+pub fn sector(stock_id: &str) -> &'static str {
+    match stock_id {
+        "AAPL" | "GOOGL" | "AMZN" | "META" | "MSFT" | "TSLA" | "NFLX" | "NVDA" | "BABA" | "ORCL" | "INTC"
+        | "CSCO" | "ADBE" | "IBM" | "AMD" | "QCOM" | "INTU" | "CRM" | "UBER" | "LYFT" | "SNAP" | "TWTR"
+        | "ZM" | "SHOP" | "ASML" | "TXN" | "ADSK" | "ROKU" => "tech",
+        "PYPL" | "V" | "SQ" | "JPM" | "BAC" | "C" | "GS" | "AXP" | "SCHW" => "finance",
+        "WMT" | "DIS" | "MCD" | "NKE" | "LOW" | "HD" | "SBUX" | "TGT" => "consumer",
+        "PFE" | "MRK" | "JNJ" | "UNH" | "CVS" | "GILD" | "AMGN" | "BMY" | "SNY" => "healthcare",
+        "XOM" | "CVX" => "energy",
+        "BA" | "GE" => "industrial",
+        "VZ" => "telecom",
+        _ => UNKNOWN_SECTOR,
+    }
+}
+
+// First word of every price update's routing key on the topic exchange.
+pub const PRICE_TOPIC: &str = "prices";
+
+// Topic routing key for a symbol's price updates, `prices.<sector>.<symbol>`, so consumers can
+// bind to a sector (`prices.tech.*`), a single symbol (`prices.*.AAPL`) or everything (`prices.#`).
+// Dots would split the symbol into extra words, so they become underscores (BRK.B -> BRK_B).
+pub fn routing_key(stock_id: &str) -> String {
+    format!("{}.{}.{}", PRICE_TOPIC, sector(stock_id), stock_id.replace('.', "_"))
+}
+
+#[derive(Debug, Clone)]
+pub struct JumpDiffusion {
+    pub intensity: f64,
+    pub mean: f64,
+    pub volatility: f64,
+}
+
+// Geometric Brownian motion per tick, with optional Merton-style jumps.
+#[derive(Debug, Clone)]
+pub struct PriceModel {
+    pub drift: f64,
+    pub volatility: f64,
+    pub dt: f64,
+    pub jumps: Option<JumpDiffusion>,
+}
+
+impl Default for PriceModel {
+    fn default() -> Self {
+        PriceModel {
+            drift: 0.0,
+            volatility: 0.05,
+            dt: 1.0,
+            jumps: None,
+        }
+    }
+}
+
+impl PriceModel {
+    pub fn step<R: Rng>(&self, price: f64, rng: &mut R) -> f64 {
+        let z: f64 = rng.sample(StandardNormal);
+        let diffusion = (self.drift - 0.5 * self.volatility * self.volatility) * self.dt
+            + self.volatility * self.dt.sqrt() * z;
+
+        let mut jump = 0.0;
+        if let Some(jumps) = &self.jumps {
+            if jumps.intensity > 0.0 {
+                let count = match Poisson::new(jumps.intensity * self.dt) {
+                    Ok(poisson) => rng.sample(poisson) as u64,
+                    Err(_) => 0,
+                };
+                for _ in 0..count {
+                    let size: f64 = rng.sample(StandardNormal);
+                    jump += jumps.mean + jumps.volatility * size;
+                }
+            }
+        }
+
+        price * (diffusion + jump).exp()
+    }
+}
+
+// Per-run drift drawn when none is configured.
+pub fn random_drift<R: Rng>(rng: &mut R) -> f64 {
+    rng.gen_range(-0.02..0.02)
+}
+
+pub struct PriceSimulator<R: Rng> {
+    rng: R,
+    model: PriceModel,
+    symbols: Vec<String>,
+    prices: Vec<f64>,
+    next_stock: usize,
+}
+
+impl PriceSimulator<StdRng> {
+    pub fn seeded(seed: u64) -> Self {
+        PriceSimulator::new(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl<R: Rng> PriceSimulator<R> {
+    // Draws the per-run drift (the old `trend`) and uses default volatility without jumps.
+    pub fn new(mut rng: R) -> Self {
+        let trend = random_drift(&mut rng);
+        let model = PriceModel {
+            drift: trend,
+            ..PriceModel::default()
+        };
+        PriceSimulator::with_model(rng, model)
+    }
+
+    pub fn with_model(rng: R, model: PriceModel) -> Self {
+        PriceSimulator::with_universe(rng, model, STOCKS.iter().map(|s| s.to_string()).collect())
+    }
+
+    // Simulates `symbols` instead of the built-in universe.
+    pub fn with_universe(mut rng: R, model: PriceModel, symbols: Vec<String>) -> Self {
+        let prices = symbols.iter().map(|_| rng.gen_range(50.0..500.0)).collect();
+        PriceSimulator {
+            rng,
+            model,
+            symbols,
+            prices,
+            next_stock: 0,
+        }
+    }
+
+    pub fn model(&self) -> &PriceModel {
+        &self.model
+    }
+
+    pub fn symbols(&self) -> &[String] {
+        &self.symbols
+    }
+
+    pub fn last_price(&self, stock_id: &str) -> Option<f64> {
+        self.symbols.iter().position(|s| s == stock_id).map(|i| self.prices[i])
+    }
+
+    // Returns the previous price alongside the update for the next stock in the universe.
+    pub fn next_update(&mut self, timestamp: String) -> (f64, TradeMessage) {
+        let index = self.next_stock;
+        self.next_stock = (self.next_stock + 1) % self.symbols.len();
+
+        let old_price = self.prices[index];
+        let new_price = self.model.step(old_price, &mut self.rng);
+        self.prices[index] = new_price;
+        let quantity = self.rng.gen_range(5..15);
+
+        let message = TradeMessage {
+            stock_id: self.symbols[index].clone(),
+            current_price: new_price,
+            action_type: MessageType::PriceUpdate,
+            quantity,
+            timestamp,
+        };
+        (old_price, message)
+    }
+
+    // Produces `ticks` updates on a simulated clock, for backtests that should not sleep.
+    pub fn generate(&mut self, ticks: usize, start: DateTime<Utc>, interval: chrono::Duration) -> Vec<TradeMessage> {
+        (0..ticks)
+            .map(|tick| {
+                let timestamp = start + interval * tick as i32;
+                self.next_update(timestamp.to_rfc3339()).1
+            })
+            .collect()
+    }
+}
 
 pub fn simulate_price_updates(
     trade_message_sender: mpsc::Sender<TradeMessage>,
     simulation_duration_secs: u64,
+    seed: Option<u64>,
+) {
+    let seed = seed.unwrap_or_else(|| rand::thread_rng().gen());
+    println!("[Producer] Using simulation seed {}.", seed);
+    simulate_price_updates_with_rng(trade_message_sender, simulation_duration_secs, StdRng::seed_from_u64(seed));
+}
+
+pub fn simulate_price_updates_with_rng<R: Rng>(
+    trade_message_sender: mpsc::Sender<TradeMessage>,
+    simulation_duration_secs: u64,
+    rng: R,
+) {
+    run_price_simulation(
+        trade_message_sender,
+        simulation_duration_secs,
+        DEFAULT_TICK_INTERVAL,
+        PriceSimulator::new(rng),
+    );
+}
+
+pub fn run_price_simulation<R: Rng>(
+    trade_message_sender: mpsc::Sender<TradeMessage>,
+    simulation_duration_secs: u64,
+    tick_interval: Duration,
+    mut simulator: PriceSimulator<R>,
 ) {
-    let stocks = vec![
-        "AAPL", "GOOGL", "AMZN", "META", "MSFT", "TSLA", "NFLX", "NVDA", 
-        "BABA", "ORCL", "INTC", "CSCO", "ADBE", "IBM", "PYPL",
-        "V", "AMD", "QCOM", "INTU", "CRM", "UBER", "LYFT", "SNAP", 
-        "TWTR", "SQ", "ZM", "SHOP", "ASML", "TXN", "ADSK",
-        "JPM", "BAC", "C", "GS", "AXP", "VZ", "SCHW",
-        "WMT", "DIS", "MCD", "NKE", "LOW", "HD", "SBUX", "TGT", "ROKU",
-        "PFE", "MRK", "JNJ", "UNH", "CVS", "GILD", "AMGN", "BMY", "SNY",
-        "XOM", "CVX", "BA", "GE"
-    ];
-    
-    let mut rng = rand::thread_rng();
     let simulation_start_time = Instant::now();
     let simulation_duration = Duration::from_secs(simulation_duration_secs);
-    let trend = rng.gen_range(-0.02..0.02);
 
-    println!("[Producer] Starting price simulation for {} seconds.", simulation_duration_secs);
+    println!(
+        "[Producer] Starting price simulation for {} seconds (drift: {:.4}, volatility: {:.4}).",
+        simulation_duration_secs, simulator.model().drift, simulator.model().volatility
+    );
 
     while Instant::now() - simulation_start_time < simulation_duration {
-        for stock_id in &stocks {
-            let old_price = rng.gen_range(50.0..500.0);
-            let new_price = old_price * (1.0 + rng.gen_range(-0.10..0.10) + trend);
+        for _ in 0..simulator.symbols().len() {
+            let (old_price, message) = simulator.next_update(Utc::now().to_rfc3339());
+            let stock_id = message.stock_id.clone();
+            let new_price = message.current_price;
             let price_change = new_price - old_price;
-            let quantity = rng.gen_range(5..15);
-
-            let message = TradeMessage {
-                stock_id: stock_id.to_string(),
-                current_price: new_price,
-                action_type: "PRICE_UPDATE".to_string(),
-                quantity,
-                timestamp: Utc::now().to_rfc3339(),
-            };
 
             println!(
                 "[Producer] Updated Price for {:<5}: ${:.2} -> ${:.2} (Change: {:.2}) at {}",
                 stock_id, old_price, new_price, price_change, message.timestamp
             );
 
+            // The receiver is gone once the consumer has shut down.
             if let Err(e) = trade_message_sender.send(message) {
                 eprintln!("[ERROR] Failed to send trade message for {}: {}", stock_id, e);
+                return;
             }
 
-            thread::sleep(Duration::from_millis(300)); 
+            thread::sleep(tick_interval);
         }
     }
 
     println!("[Producer] Simulation completed after {} seconds.", simulation_duration_secs);
 }
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    RealTime,
+    Accelerated(f64),
+    AsFastAsPossible,
+}
+
+impl FromStr for ReplaySpeed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "realtime" | "wall-clock" | "1x" => Ok(ReplaySpeed::RealTime),
+            "max" | "fast" | "asap" => Ok(ReplaySpeed::AsFastAsPossible),
+            other => {
+                let multiplier: f64 = other
+                    .trim_end_matches('x')
+                    .parse()
+                    .map_err(|_| format!("invalid replay speed '{}': use realtime, max or a multiplier like 10x", s))?;
+                if multiplier > 0.0 {
+                    Ok(ReplaySpeed::Accelerated(multiplier))
+                } else {
+                    Err(format!("replay speed multiplier must be positive, got {}", multiplier))
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OhlcvBar {
+    pub timestamp: DateTime<Utc>,
+    pub symbol: String,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+impl OhlcvBar {
+    pub fn to_message(&self) -> TradeMessage {
+        TradeMessage {
+            stock_id: self.symbol.clone(),
+            current_price: self.close,
+            action_type: MessageType::PriceUpdate,
+            quantity: self.volume.max(0.0).min(u32::MAX as f64) as u32,
+            timestamp: self.timestamp.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CsvBar {
+    timestamp: String,
+    symbol: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: f64,
+}
+
+fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    let raw = raw.trim();
+    if let Ok(ts) = DateTime::parse_from_rfc3339(raw) {
+        return Some(ts.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(ts) = NaiveDateTime::parse_from_str(raw, format) {
+            return Some(ts.and_utc());
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0).map(|ts| ts.and_utc());
+    }
+    raw.parse::<i64>().ok().and_then(|secs| DateTime::from_timestamp(secs, 0))
+}
+
+// Reads timestamp,symbol,open,high,low,close,volume rows (headers are case-insensitive).
+pub fn load_ohlcv_csv<P: AsRef<Path>>(path: P) -> io::Result<Vec<OhlcvBar>> {
+    let path = path.as_ref();
+    let mut reader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_path(path)?;
+    let headers: csv::StringRecord = reader.headers()?.iter().map(|h| h.to_ascii_lowercase()).collect();
+    reader.set_headers(headers);
+
+    let mut bars = Vec::new();
+    for (line, row) in reader.deserialize::<CsvBar>().enumerate() {
+        let row = row?;
+        let timestamp = parse_timestamp(&row.timestamp).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: row {} has unparseable timestamp '{}'", path.display(), line + 2, row.timestamp),
+            )
+        })?;
+        bars.push(OhlcvBar {
+            timestamp,
+            symbol: row.symbol,
+            open: row.open,
+            high: row.high,
+            low: row.low,
+            close: row.close,
+            volume: row.volume,
+        });
+    }
+    Ok(bars)
+}
+
+// Loads every file and merges the bars into a single timestamp-ordered stream.
+pub fn load_ohlcv_files<P: AsRef<Path>>(paths: &[P]) -> io::Result<Vec<OhlcvBar>> {
+    let mut bars = Vec::new();
+    for path in paths {
+        bars.extend(load_ohlcv_csv(path)?);
+    }
+    bars.sort_by_key(|bar| bar.timestamp);
+    Ok(bars)
+}
+
+pub fn replay_price_updates(
+    trade_message_sender: mpsc::Sender<TradeMessage>,
+    bars: Vec<OhlcvBar>,
+    speed: ReplaySpeed,
+) {
+    println!("[Producer] Replaying {} historical bars at {:?}.", bars.len(), speed);
+
+    let mut previous: Option<DateTime<Utc>> = None;
+    for bar in &bars {
+        if let Some(previous) = previous {
+            let gap = (bar.timestamp - previous).to_std().unwrap_or(Duration::ZERO);
+            match speed {
+                ReplaySpeed::RealTime => thread::sleep(gap),
+                ReplaySpeed::Accelerated(multiplier) => thread::sleep(gap.div_f64(multiplier)),
+                ReplaySpeed::AsFastAsPossible => {}
+            }
+        }
+        previous = Some(bar.timestamp);
+
+        let message = bar.to_message();
+        println!(
+            "[Producer] Replayed Price for {:<5}: ${:.2} (O: {:.2} H: {:.2} L: {:.2}) at {}",
+            bar.symbol, bar.close, bar.open, bar.high, bar.low, message.timestamp
+        );
+
+        if let Err(e) = trade_message_sender.send(message) {
+            eprintln!("[ERROR] Failed to send replayed message for {}: {}", bar.symbol, e);
+            break;
+        }
+    }
+
+    println!("[Producer] Replay completed.");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(simulator: &mut PriceSimulator<StdRng>) -> Vec<(String, f64, u32)> {
+        let start = DateTime::parse_from_rfc3339("2024-01-02T14:30:00Z").unwrap().with_timezone(&Utc);
+        simulator
+            .generate(3 * STOCKS.len(), start, chrono::Duration::seconds(1))
+            .into_iter()
+            .map(|message| (message.stock_id, message.current_price, message.quantity))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_prices() {
+        assert_eq!(run(&mut PriceSimulator::seeded(42)), run(&mut PriceSimulator::seeded(42)));
+        assert_ne!(run(&mut PriceSimulator::seeded(42)), run(&mut PriceSimulator::seeded(43)));
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_jumps() {
+        let model = PriceModel {
+            drift: 0.001,
+            volatility: 0.02,
+            dt: 1.0,
+            jumps: Some(JumpDiffusion {
+                intensity: 0.5,
+                mean: -0.05,
+                volatility: 0.1,
+            }),
+        };
+        let simulate = |seed| run(&mut PriceSimulator::with_model(StdRng::seed_from_u64(seed), model.clone()));
+        assert_eq!(simulate(7), simulate(7));
+        assert_ne!(simulate(7), simulate(8));
+    }
+}
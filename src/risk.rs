@@ -0,0 +1,253 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::models::TradeAction;
+use crate::portfolio::Portfolio;
+use crate::producer::sector;
+
+// Every limit is optional; an unset limit is not enforced.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskLimits {
+    // Absolute net market value of one symbol.
+    pub max_position_value: Option<f64>,
+    // Long plus short market value.
+    pub max_gross_exposure: Option<f64>,
+    // Absolute long minus short market value.
+    pub max_net_exposure: Option<f64>,
+    // Gross exposure of one sector as a fraction of equity.
+    pub max_sector_concentration: Option<f64>,
+    pub max_order_notional: Option<f64>,
+    pub max_orders_per_sec: Option<u32>,
+    // Loss from the start-of-day equity at which trading halts for the rest of the day.
+    pub daily_loss_limit: Option<f64>,
+}
+
+impl RiskLimits {
+    pub fn validate(&self) -> Result<(), String> {
+        let values = [
+            ("max_position_value", self.max_position_value),
+            ("max_gross_exposure", self.max_gross_exposure),
+            ("max_net_exposure", self.max_net_exposure),
+            ("max_sector_concentration", self.max_sector_concentration),
+            ("max_order_notional", self.max_order_notional),
+            ("daily_loss_limit", self.daily_loss_limit),
+        ];
+        for (name, value) in values {
+            if value.is_some_and(|value| value <= 0.0) {
+                return Err(format!("{} must be positive", name));
+            }
+        }
+        if self.max_orders_per_sec == Some(0) {
+            return Err("max_orders_per_sec must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+pub fn load_risk_limits<P: AsRef<Path>>(path: P) -> io::Result<RiskLimits> {
+    let limits: RiskLimits = serde_json::from_slice(&fs::read(path)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    limits
+        .validate()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(limits)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "reason", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RiskRejection {
+    PositionLimit { stock_id: String, limit: f64, projected: f64 },
+    GrossExposure { limit: f64, projected: f64 },
+    NetExposure { limit: f64, projected: f64 },
+    SectorConcentration { sector: String, limit: f64, projected: f64 },
+    OrderNotional { limit: f64, notional: f64 },
+    OrderRate { limit: u32 },
+    DailyLossLimit { limit: f64, loss: f64 },
+    // Trading was halted from the control channel, for every symbol when `stock_id` is empty.
+    Halted { stock_id: Option<String> },
+}
+
+impl fmt::Display for RiskRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskRejection::PositionLimit { stock_id, limit, projected } => {
+                write!(f, "position in {} would be ${:.2} (limit ${:.2})", stock_id, projected, limit)
+            }
+            RiskRejection::GrossExposure { limit, projected } => {
+                write!(f, "gross exposure would be ${:.2} (limit ${:.2})", projected, limit)
+            }
+            RiskRejection::NetExposure { limit, projected } => {
+                write!(f, "net exposure would be ${:.2} (limit ${:.2})", projected, limit)
+            }
+            RiskRejection::SectorConcentration { sector, limit, projected } => write!(
+                f,
+                "{} sector would be {:.1}% of equity (limit {:.1}%)",
+                sector,
+                projected * 100.0,
+                limit * 100.0
+            ),
+            RiskRejection::OrderNotional { limit, notional } => {
+                write!(f, "order notional ${:.2} exceeds ${:.2}", notional, limit)
+            }
+            RiskRejection::OrderRate { limit } => write!(f, "more than {} orders in one second", limit),
+            RiskRejection::DailyLossLimit { limit, loss } => {
+                write!(f, "trading halted: daily loss ${:.2} reached limit ${:.2}", loss, limit)
+            }
+            RiskRejection::Halted { stock_id: Some(stock_id) } => write!(f, "trading in {} is halted", stock_id),
+            RiskRejection::Halted { stock_id: None } => f.write_str("all trading is halted"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RiskEngine {
+    limits: RiskLimits,
+    // Event times of recently accepted orders, for the rate limit.
+    recent_orders: VecDeque<DateTime<Utc>>,
+    day: Option<NaiveDate>,
+    day_start_equity: f64,
+    halted: bool,
+}
+
+impl RiskEngine {
+    pub fn new(limits: RiskLimits) -> Self {
+        RiskEngine {
+            limits,
+            recent_orders: VecDeque::new(),
+            day: None,
+            day_start_equity: 0.0,
+            halted: false,
+        }
+    }
+
+    pub fn limits(&self) -> &RiskLimits {
+        &self.limits
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    // Loss since the start of the portfolio clock's day; negative when the day is up.
+    pub fn daily_loss(&self, portfolio: &Portfolio) -> f64 {
+        self.day_start_equity - portfolio.equity()
+    }
+
+    fn roll_day(&mut self, portfolio: &Portfolio) {
+        let today = portfolio.clock.date_naive();
+        if self.day != Some(today) {
+            if self.halted {
+                println!("[Risk] New trading day {}: daily loss halt lifted.", today);
+            }
+            self.day = Some(today);
+            self.day_start_equity = portfolio.equity();
+            self.halted = false;
+        }
+    }
+
+    // Runs every limit against the order as if it filled in full at `price`; accepted orders count towards the rate limit.
+    pub fn check(
+        &mut self,
+        portfolio: &Portfolio,
+        stock_id: &str,
+        action: TradeAction,
+        quantity: u32,
+        price: f64,
+    ) -> Result<(), RiskRejection> {
+        self.roll_day(portfolio);
+
+        if let Some(limit) = self.limits.daily_loss_limit {
+            let loss = self.daily_loss(portfolio);
+            if self.halted || loss >= limit {
+                if !self.halted {
+                    println!("[Risk] Daily loss ${:.2} reached limit ${:.2}: trading halted for the day.", loss, limit);
+                }
+                self.halted = true;
+                return Err(RiskRejection::DailyLossLimit { limit, loss });
+            }
+        }
+
+        let notional = price * quantity as f64;
+        if let Some(limit) = self.limits.max_order_notional {
+            if notional > limit {
+                return Err(RiskRejection::OrderNotional { limit, notional });
+            }
+        }
+
+        let now = portfolio.clock;
+        while self.recent_orders.front().is_some_and(|time| now - *time >= Duration::seconds(1)) {
+            self.recent_orders.pop_front();
+        }
+        if let Some(limit) = self.limits.max_orders_per_sec {
+            if self.recent_orders.len() >= limit as usize {
+                return Err(RiskRejection::OrderRate { limit });
+            }
+        }
+
+        // Sells and covers only ever reduce exposure, so the exposure limits apply to opening orders.
+        let signed = match action {
+            TradeAction::Buy => notional,
+            TradeAction::Short => -notional,
+            _ => 0.0,
+        };
+        if signed != 0.0 {
+            self.check_exposure(portfolio, stock_id, signed, price)?;
+        }
+
+        self.recent_orders.push_back(now);
+        Ok(())
+    }
+
+    fn check_exposure(&self, portfolio: &Portfolio, stock_id: &str, signed: f64, price: f64) -> Result<(), RiskRejection> {
+        if let Some(limit) = self.limits.max_position_value {
+            let projected = (portfolio.net_position(stock_id) as f64 * price + signed).abs();
+            if projected > limit {
+                return Err(RiskRejection::PositionLimit { stock_id: stock_id.to_string(), limit, projected });
+            }
+        }
+
+        let long = portfolio.market_value();
+        let short = portfolio.short_market_value();
+        if let Some(limit) = self.limits.max_gross_exposure {
+            let projected = long + short + signed.abs();
+            if projected > limit {
+                return Err(RiskRejection::GrossExposure { limit, projected });
+            }
+        }
+        if let Some(limit) = self.limits.max_net_exposure {
+            let projected = (long - short + signed).abs();
+            if projected > limit {
+                return Err(RiskRejection::NetExposure { limit, projected });
+            }
+        }
+
+        if let Some(limit) = self.limits.max_sector_concentration {
+            let target = sector(stock_id);
+            let sector_exposure: f64 = portfolio
+                .holdings
+                .iter()
+                .map(|(stock, (quantity, avg_cost))| (stock, *quantity, *avg_cost))
+                .chain(portfolio.shorts.iter().map(|(stock, position)| (stock, position.quantity, position.avg_price)))
+                .filter(|(stock, _, _)| sector(stock) == target)
+                .map(|(stock, quantity, fallback)| {
+                    quantity as f64 * portfolio.last_prices.get(stock).cloned().unwrap_or(fallback)
+                })
+                .sum();
+            let equity = portfolio.equity();
+            let projected = if equity > 0.0 {
+                (sector_exposure + signed.abs()) / equity
+            } else {
+                f64::INFINITY
+            };
+            if projected > limit {
+                return Err(RiskRejection::SectorConcentration { sector: target.to_string(), limit, projected });
+            }
+        }
+        Ok(())
+    }
+}
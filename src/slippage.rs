@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use crate::models::TradeAction;
+
+// RiskMetrics decay for the per-symbol EWMA variance of tick returns.
+pub const EWMA_LAMBDA: f64 = 0.94;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum SlippageModel {
+    #[default]
+    None,
+    // A constant cost in basis points of the quoted price.
+    FixedBps { bps: f64 },
+    // A multiple of the symbol's per-tick return volatility.
+    VolatilityScaled { multiplier: f64 },
+    // coefficient * volatility * sqrt(order quantity / quoted quantity).
+    SquareRootImpact { coefficient: f64 },
+}
+
+impl SlippageModel {
+    // Fractional price move against the order; always >= 0.
+    pub fn slippage(&self, quantity: u32, available: u32, volatility: f64) -> f64 {
+        match *self {
+            SlippageModel::None => 0.0,
+            SlippageModel::FixedBps { bps } => bps / 10_000.0,
+            SlippageModel::VolatilityScaled { multiplier } => multiplier * volatility,
+            SlippageModel::SquareRootImpact { coefficient } => {
+                let participation = quantity as f64 / available.max(1) as f64;
+                coefficient * volatility * participation.sqrt()
+            }
+        }
+        .max(0.0)
+    }
+
+    // Price actually paid or received: buys and covers fill above the quote, sells and shorts below it.
+    pub fn fill_price(&self, action: TradeAction, quoted: f64, quantity: u32, available: u32, volatility: f64) -> f64 {
+        let slippage = self.slippage(quantity, available, volatility);
+        match action {
+            TradeAction::Buy | TradeAction::Cover => quoted * (1.0 + slippage),
+            _ => quoted * (1.0 - slippage),
+        }
+    }
+}
+
+// Parses `none`, `fixed:<bps>`, `vol:<multiplier>` or `sqrt:<coefficient>`.
+impl FromStr for SlippageModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let spec = s.trim().to_ascii_lowercase();
+        if spec == "none" {
+            return Ok(SlippageModel::None);
+        }
+        let (kind, value) = spec
+            .split_once(':')
+            .ok_or_else(|| format!("unknown slippage model '{}': use none, fixed:<bps>, vol:<k> or sqrt:<k>", s))?;
+        let value: f64 = value
+            .parse()
+            .ok()
+            .filter(|value: &f64| *value >= 0.0)
+            .ok_or_else(|| format!("slippage parameter in '{}' must be a non-negative number", s))?;
+        match kind {
+            "fixed" | "bps" => Ok(SlippageModel::FixedBps { bps: value }),
+            "vol" | "volatility" => Ok(SlippageModel::VolatilityScaled { multiplier: value }),
+            "sqrt" | "impact" => Ok(SlippageModel::SquareRootImpact { coefficient: value }),
+            _ => Err(format!("unknown slippage model '{}': use none, fixed:<bps>, vol:<k> or sqrt:<k>", s)),
+        }
+    }
+}
+
+// Per-symbol EWMA volatility of tick-to-tick log returns, fed from the price stream.
+#[derive(Debug, Clone)]
+pub struct VolatilityEstimator {
+    lambda: f64,
+    // stock -> (last price, EWMA variance once a return has been seen)
+    stats: HashMap<String, (f64, Option<f64>)>,
+}
+
+impl Default for VolatilityEstimator {
+    fn default() -> Self {
+        VolatilityEstimator::new(EWMA_LAMBDA)
+    }
+}
+
+impl VolatilityEstimator {
+    pub fn new(lambda: f64) -> Self {
+        VolatilityEstimator {
+            lambda,
+            stats: HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, stock_id: &str, price: f64) {
+        if price <= 0.0 {
+            return;
+        }
+        let lambda = self.lambda;
+        match self.stats.get_mut(stock_id) {
+            Some((last, variance)) => {
+                let squared = (price / *last).ln().powi(2);
+                *variance = Some(match *variance {
+                    Some(previous) => lambda * previous + (1.0 - lambda) * squared,
+                    None => squared,
+                });
+                *last = price;
+            }
+            None => {
+                self.stats.insert(stock_id.to_string(), (price, None));
+            }
+        }
+    }
+
+    // Zero until the symbol has at least one observed return.
+    pub fn volatility(&self, stock_id: &str) -> f64 {
+        self.stats
+            .get(stock_id)
+            .and_then(|(_, variance)| *variance)
+            .map(f64::sqrt)
+            .unwrap_or(0.0)
+    }
+}
@@ -0,0 +1,184 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use crate::portfolio::Portfolio;
+
+pub const SNAPSHOT_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    Json,
+    Binary,
+}
+
+impl SnapshotFormat {
+    // `.json` files are JSON, everything else uses the compact binary encoding.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => SnapshotFormat::Json,
+            _ => SnapshotFormat::Binary,
+        }
+    }
+}
+
+impl FromStr for SnapshotFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(SnapshotFormat::Json),
+            "bin" | "binary" | "bincode" => Ok(SnapshotFormat::Binary),
+            _ => Err(format!("unknown snapshot format '{}': use json or binary", s)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snapshot {
+    pub version: u32,
+    pub saved_at: DateTime<Utc>,
+    pub journal_seq: Option<u64>,
+    pub portfolio: Portfolio,
+}
+
+fn invalid_data<E: std::fmt::Display>(e: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+pub fn encode_snapshot(snapshot: &Snapshot, format: SnapshotFormat) -> io::Result<Vec<u8>> {
+    match format {
+        SnapshotFormat::Json => serde_json::to_vec_pretty(snapshot).map_err(invalid_data),
+        SnapshotFormat::Binary => bincode::serialize(snapshot).map_err(invalid_data),
+    }
+}
+
+pub fn decode_snapshot(bytes: &[u8], format: SnapshotFormat) -> io::Result<Snapshot> {
+    let snapshot: Snapshot = match format {
+        SnapshotFormat::Json => serde_json::from_slice(bytes).map_err(invalid_data)?,
+        SnapshotFormat::Binary => bincode::deserialize(bytes).map_err(invalid_data)?,
+    };
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(invalid_data(format!(
+            "unsupported snapshot version {} (expected {})",
+            snapshot.version, SNAPSHOT_VERSION
+        )));
+    }
+    Ok(snapshot)
+}
+
+// Writes to a temporary file first so a crash mid-write never leaves a truncated snapshot behind.
+pub fn save_snapshot<P: AsRef<Path>>(portfolio: &Portfolio, path: P) -> io::Result<()> {
+    save_snapshot_at(portfolio, portfolio.journal_seq(), path)
+}
+
+// Saves a portfolio that was rebuilt from a journal rather than journaling itself, recording
+// the last journal sequence it reflects.
+pub fn save_snapshot_at<P: AsRef<Path>>(portfolio: &Portfolio, journal_seq: Option<u64>, path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    let snapshot = Snapshot {
+        version: SNAPSHOT_VERSION,
+        saved_at: Utc::now(),
+        journal_seq,
+        portfolio: portfolio.clone(),
+    };
+    let bytes = encode_snapshot(&snapshot, SnapshotFormat::from_path(path))?;
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    fs::write(&tmp_path, bytes)?;
+    fs::rename(&tmp_path, path)
+}
+
+pub fn load_snapshot<P: AsRef<Path>>(path: P) -> io::Result<Snapshot> {
+    let path = path.as_ref();
+    let bytes = fs::read(path)?;
+    decode_snapshot(&bytes, SnapshotFormat::from_path(path))
+}
+
+pub struct Autosaver {
+    pub path: PathBuf,
+    pub interval: Duration,
+    last_saved: Instant,
+}
+
+impl Autosaver {
+    pub fn new(path: PathBuf, interval: Duration) -> Self {
+        Autosaver {
+            path,
+            interval,
+            last_saved: Instant::now(),
+        }
+    }
+
+    pub fn save(&mut self, portfolio: &Portfolio) {
+        match save_snapshot(portfolio, &self.path) {
+            Ok(()) => println!("[Snapshot] Portfolio saved to {}.", self.path.display()),
+            Err(e) => eprintln!("[ERROR] Failed to save snapshot to {}: {}", self.path.display(), e),
+        }
+        self.last_saved = Instant::now();
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.last_saved.elapsed() >= self.interval
+    }
+
+    pub fn maybe_save(&mut self, portfolio: &Portfolio) {
+        if self.is_due() {
+            self.save(portfolio);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::FeeModel;
+    use crate::lots::CostBasisMethod;
+    use crate::margin::MarginConfig;
+    use crate::models::TradeAction;
+
+    fn traded_portfolio(fees: &str) -> Portfolio {
+        let mut portfolio = Portfolio::with_cost_basis(10000.0, CostBasisMethod::Fifo)
+            .with_margin(MarginConfig::default())
+            .with_fee_model(fees.parse::<FeeModel>().unwrap());
+        portfolio.update("AAPL", 10, 150.0, TradeAction::Buy);
+        portfolio.update("AAPL", 5, 160.0, TradeAction::Buy);
+        portfolio.update("AAPL", 8, 170.0, TradeAction::Sell);
+        portfolio.update("TSLA", 4, 200.0, TradeAction::Short);
+        portfolio.update_last_price("AAPL", 165.0);
+        portfolio.update_last_price("TSLA", 190.0);
+        portfolio
+    }
+
+    fn assert_round_trip(path: &Path) {
+        for fees in ["flat", "per-share", "tiered", "maker-taker"] {
+            let portfolio = traded_portfolio(fees);
+            save_snapshot(&portfolio, path).unwrap();
+            let restored = load_snapshot(path).unwrap();
+            assert_eq!(restored.version, SNAPSHOT_VERSION);
+            assert_eq!(
+                serde_json::to_value(&restored.portfolio).unwrap(),
+                serde_json::to_value(&portfolio).unwrap(),
+                "{} fees, {}",
+                fees,
+                path.display()
+            );
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn json_snapshot_round_trip() {
+        assert_round_trip(&std::env::temp_dir().join(format!("snapshot-test-{}.json", std::process::id())));
+    }
+
+    #[test]
+    fn binary_snapshot_round_trip() {
+        assert_round_trip(&std::env::temp_dir().join(format!("snapshot-test-{}.bin", std::process::id())));
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use crate::models::TradeMessage;
+
+pub const DEFAULT_WORKERS: usize = 4;
+pub const DEFAULT_QUEUE_CAPACITY: usize = 32;
+
+// A fixed set of decision workers, each with its own bounded queue. Every job for a symbol goes
+// to the same worker, so updates for one symbol are handled in the order they arrived. Jobs are
+// plain `TradeMessage`s unless the caller needs to carry more with them, such as where to reply.
+// A job whose handler panics is counted and skipped; its worker carries on with the next one.
+//
+// Sharding orders each symbol's updates; it does not make decisions parallel when every handler
+// locks the same state. The live consumer's handlers share one portfolio and one order manager, so
+// its decisions still run one at a time, and the workers only overlap queueing with deciding.
+pub struct WorkerPool<T = TradeMessage> {
+    shards: Vec<SyncSender<T>>,
+    handles: Vec<JoinHandle<()>>,
+    // Messages queued or being handled.
+    in_flight: Arc<AtomicUsize>,
+    panicked: Arc<AtomicUsize>,
+}
+
+impl<T: Send + 'static> WorkerPool<T> {
+    pub fn new<F>(workers: usize, queue_capacity: usize, handler: F) -> Self
+    where
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        let handler = Arc::new(handler);
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let panicked = Arc::new(AtomicUsize::new(0));
+        let mut shards = Vec::with_capacity(workers);
+        let mut handles = Vec::with_capacity(workers);
+        for index in 0..workers.max(1) {
+            let (sender, receiver) = mpsc::sync_channel::<T>(queue_capacity);
+            let handler = Arc::clone(&handler);
+            let in_flight = Arc::clone(&in_flight);
+            let panicked = Arc::clone(&panicked);
+            let handle = thread::Builder::new()
+                .name(format!("decision-worker-{}", index))
+                .spawn(move || {
+                    for job in receiver {
+                        if panic::catch_unwind(AssertUnwindSafe(|| handler(job))).is_err() {
+                            eprintln!("[ERROR] decision-worker-{} panicked handling a job; skipping it.", index);
+                            panicked.fetch_add(1, Ordering::SeqCst);
+                        }
+                        in_flight.fetch_sub(1, Ordering::SeqCst);
+                    }
+                })
+                .expect("failed to spawn decision worker");
+            shards.push(sender);
+            handles.push(handle);
+        }
+        WorkerPool {
+            shards,
+            handles,
+            in_flight,
+            panicked,
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    pub fn shard_for(&self, stock_id: &str) -> usize {
+        let mut hasher = DefaultHasher::new();
+        stock_id.hash(&mut hasher);
+        (hasher.finish() % self.shards.len() as u64) as usize
+    }
+
+    // Queues the job on the worker for `stock_id`, handing it back when that queue is full.
+    pub fn try_submit(&self, stock_id: &str, job: T) -> Result<(), TrySendError<T>> {
+        let shard = self.shard_for(stock_id);
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        self.shards[shard].try_send(job).inspect_err(|_| {
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+        })
+    }
+
+    pub fn panicked(&self) -> usize {
+        self.panicked.load(Ordering::SeqCst)
+    }
+
+    // Closes every queue and waits for the workers to finish what was already queued.
+    // Returns the number of jobs whose handler panicked.
+    pub fn shutdown(self) -> usize {
+        drop(self.shards);
+        let died = self.handles.into_iter().filter_map(|handle| handle.join().err()).count();
+        self.panicked.load(Ordering::SeqCst) + died
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn a_panicking_job_does_not_stop_its_worker() {
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let pool = {
+            let handled = Arc::clone(&handled);
+            WorkerPool::new(1, 8, move |job: u32| {
+                assert_ne!(job, 2, "bad job");
+                handled.lock().unwrap().push(job);
+            })
+        };
+        for job in 1..=4 {
+            pool.try_submit("AAPL", job).unwrap();
+        }
+        assert_eq!(pool.shutdown(), 1);
+        assert_eq!(*handled.lock().unwrap(), vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn in_flight_drops_back_to_zero_after_a_panic() {
+        let pool = WorkerPool::new(2, 8, |job: u32| assert_ne!(job, 0, "bad job"));
+        pool.try_submit("AAPL", 0).unwrap();
+        pool.try_submit("AAPL", 1).unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while pool.in_flight() > 0 && std::time::Instant::now() < deadline {
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert_eq!((pool.in_flight(), pool.panicked()), (0, 1));
+        assert_eq!(pool.shutdown(), 1);
+    }
+}